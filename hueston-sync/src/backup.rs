@@ -0,0 +1,82 @@
+use hueston::{HueArchive, HueBridgeClient, HueResourceKind};
+use std::fs::File;
+use std::path::Path;
+use super::errors::*;
+
+/// Export the datastore of every bridge into `<dir>/<bridge id>.json`.
+pub fn backup_bridges(clients: &[HueBridgeClient], dir: &str) -> Result<()> {
+    for client in clients {
+        let bridge_id = client.get_bridge_id();
+
+        // Export the datastore
+        let archive = client
+            .export_datastore()
+            .chain_err(|| format!("Unable to export bridge {}.", bridge_id))?;
+
+        // Write the archive
+        let path = Path::new(dir).join(format!("{}.json", bridge_id));
+        let file = File::create(&path)
+            .chain_err(|| format!("Unable to create '{}'.", path.display()))?;
+        serde_json::to_writer_pretty(file, &archive)
+            .chain_err(|| "Unable to write archive.")?;
+
+        println!("[Bridge {}] Saved to {}", bridge_id, path.display());
+    }
+    Ok(())
+}
+
+/// Import an archive into a bridge.
+///
+/// The bridge can be omitted if there is only one.
+pub fn restore_bridge(clients: &[HueBridgeClient], path: &str, bridge_id: Option<&str>) -> Result<()> {
+
+    // Read the archive
+    let file = File::open(path)
+        .chain_err(|| format!("Unable to open '{}'.", path))?;
+    let archive: HueArchive = serde_json::from_reader(file)
+        .chain_err(|| "Invalid archive.")?;
+
+    // Pick the target bridge
+    let client = match bridge_id {
+        Some(id) => clients.iter().find(|client| client.get_bridge_id().eq_ignore_ascii_case(id)),
+        None if clients.len() == 1 => clients.first(),
+        None => bail!("Found multiple bridges, please choose one with --bridge."),
+    };
+    let client = client.chain_err(|| "Unable to find the target bridge.")?;
+
+    // Warn if the archive does not fit onto the bridge
+    if let Some(features) = client.get_features() {
+        let counts = vec![
+            (HueResourceKind::Groups, archive.groups.len()),
+            (HueResourceKind::Scenes, archive.scenes.len()),
+            (HueResourceKind::Schedules, archive.schedules.len()),
+            (HueResourceKind::Rules, archive.rules.len()),
+            (HueResourceKind::Sensors, archive.sensors.len()),
+            (HueResourceKind::ResourceLinks, archive.resourcelinks.len()),
+        ];
+        for (kind, count) in counts {
+            if !features.fits(kind, count as u32) {
+                let available = features.available(kind).unwrap_or(0);
+                println!("Warning: The archive contains {} {}, but only {} fit.", count, kind, available);
+            }
+        }
+    }
+
+    // Import the archive
+    println!("[Bridge {}] Restoring {}", client.get_bridge_id(), archive.bridge_id);
+    let report = client
+        .import_datastore(&archive)
+        .chain_err(|| "Unable to restore archive.")?;
+
+    // Report the outcome
+    for (kind, ids) in &report.ids {
+        println!("Restored {} {}", ids.len(), kind);
+    }
+    for (address, err) in &report.failures {
+        println!("Unable to restore {}: {}", address, err);
+    }
+    for (address, reference) in &report.dropped {
+        println!("Left {} out of {}, it does not exist on the bridge", reference, address);
+    }
+    Ok(())
+}
\ No newline at end of file
@@ -1,4 +1,4 @@
-use hueston::{Hueston, HueErrorCode};
+use hueston::{BridgeProfile, DiscoveryEvent, DiscoveryOptions, Hueston, HueErrorCode};
 use crate::state::StartupState;
 
 pub enum DiscoveryMode {
@@ -28,17 +28,35 @@ pub fn discover_bridges(state: &mut StartupState, mode: DiscoveryMode) {
 
     // Discover bridges
     println!("Discovering Hue bridges...");
-    let bridges = match Hueston::discover_bridges() {
-        Some(vec) => vec,
-        None => {
+    let options = DiscoveryOptions::default();
+    let bridges = Hueston::discover_bridges_with(&options, |event| match event {
+        DiscoveryEvent::Found(bridge) => println!("Found: {}", bridge.get_name()),
+        DiscoveryEvent::Failed { location, error } => {
+            println!("Unable to query bridge at {}: {}", location, error)
+        }
+    });
+    let bridges = match bridges {
+        Ok(ref vec) if vec.is_empty() => {
             println!("Unable to find Hue bridge.");
             return
         }
+        Ok(vec) => vec,
+        Err(err) => {
+            println!("Unable to discover Hue bridges: {}", err);
+            return
+        }
     };
 
     // Iterate over all bridges
     for mut bridge in bridges {
-        println!("Found: {}", bridge.get_name());
+        println!("Registering: {}", bridge.get_name());
+
+        // Compatible gateways are unlocked differently
+        match bridge.detect_profile() {
+            Ok(BridgeProfile::Philips) => (),
+            Ok(profile) => println!("Detected a {} gateway.", profile),
+            Err(err) => println!("Unable to detect the gateway type: {}", err),
+        }
         let mut waiting_for_confirmation = false;
 
         // Loop until the registration is done
@@ -66,7 +84,7 @@ pub fn discover_bridges(state: &mut StartupState, mode: DiscoveryMode) {
 
                         // Prompt the user to press the link button
                         if !waiting_for_confirmation {
-                            println!("Please press the link button on the bridge.");
+                            println!("{}", bridge.get_profile().get_pairing_hint());
                             waiting_for_confirmation = true;
                         }
 
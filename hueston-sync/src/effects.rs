@@ -1,4 +1,274 @@
 mod fireplace;
 pub use self::fireplace::FireplaceSimulation;
 mod colorsweep;
-pub use self::colorsweep::ColorsweepEffect;
\ No newline at end of file
+pub use self::colorsweep::ColorsweepEffect;
+mod params;
+pub use self::params::{ParamKind, ParamSpec, Params};
+mod registry;
+pub use self::registry::{find_effect, simulate_command, EffectInfo};
+mod scheduler;
+pub use self::scheduler::{EffectControl, Scheduler, StopReason, DEFAULT_RATE};
+mod options;
+pub use self::options::{parse_duration, run_args, EndAction, RunOptions};
+mod assign;
+pub use self::assign::Assignment;
+mod presets;
+pub use self::presets::{PresetFile, DEFAULT_PRESET_FILE};
+mod tuning;
+pub use self::tuning::{spawn_stdin_tuner, Adjustment, TuneCommand};
+mod reload;
+pub use self::reload::spawn_preset_watcher;
+mod playlist;
+pub use self::playlist::{run_playlist, Playlist};
+
+use hueston::{HueError, HueHome, HueLightBatch, HueLightState, LightSnapshot};
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use crate::errors::ResultExt;
+
+/// A light driven by an effect.
+#[derive(Debug, Clone)]
+pub struct EffectLight {
+
+    /// Unique ID of the light
+    pub uid: String,
+
+    /// Light ID on its bridge
+    pub id: usize,
+}
+
+/// A single step of an effect for one light.
+pub struct Frame {
+
+    /// Commands to send, if anything changes
+    pub batch: Option<HueLightBatch>,
+
+    /// Time until the next frame of the light
+    pub delay: Duration,
+}
+
+/// An effect driving a set of lights.
+///
+/// Effects are registered in `EFFECTS`, which also
+/// generates their command-line interface.
+pub trait Effect: Send {
+
+    /// Prepare a light before its first frame, e.g. turn it on.
+    fn init(&mut self, light: &EffectLight, state: &HueLightState) -> Option<HueLightBatch>;
+
+    /// Compute the next frame of a light.
+    fn tick(&mut self, light: &EffectLight) -> Frame;
+
+    /// Apply changed parameters while running.
+    fn update(&mut self, params: &Params);
+
+    /// Leave a light once the effect stops.
+    fn teardown(&mut self, _light: &EffectLight) -> Option<HueLightBatch> {
+        None
+    }
+}
+
+/// An effect with its parameters, ready to run on a set of lights.
+pub struct EffectSetup {
+
+    /// The effect to run
+    pub info: &'static EffectInfo,
+
+    /// Preset the parameters start from
+    pub preset: Option<String>,
+
+    /// Parameters given explicitly, taking precedence over the preset
+    pub overrides: Params,
+
+    /// Effective parameters
+    pub params: Params,
+
+    /// Unique IDs of the lights driven by the effect
+    pub lights: Vec<String>,
+
+    /// Target the effect was assigned to, e.g. `living`
+    pub target: Option<String>,
+}
+
+impl EffectSetup {
+
+    /// Construct a new `EffectSetup`, combining the preset and overrides.
+    pub fn new(
+        info: &'static EffectInfo,
+        preset: Option<&str>,
+        overrides: Params,
+        lights: Vec<String>,
+        presets: &PresetFile,
+    ) -> crate::errors::Result<Self> {
+        let mut setup = Self {
+            info,
+            preset: preset.map(String::from),
+            params: overrides.clone(),
+            overrides,
+            lights,
+            target: None,
+        };
+        setup.params = setup.resolve(presets)?;
+        Ok(setup)
+    }
+
+    /// Compute the effective parameters from a preset file.
+    pub fn resolve(&self, presets: &PresetFile) -> crate::errors::Result<Params> {
+        let mut params = Params::new(self.info.params);
+        if let Some(preset) = &self.preset {
+            presets.get(self.info.name, preset)?.apply(&mut params)?;
+        }
+        params.merge(&self.overrides);
+        Ok(params)
+    }
+}
+
+/// Calculate the random range for a base value and its variance.
+///
+/// The upper bound is exclusive, and the range is never empty.
+pub fn bounds(base: u8, variance: u8, min: u8) -> (u8, u8) {
+    let upper = base.saturating_add(variance.saturating_add(1));
+    let lower = base.saturating_sub(variance).max(min).min(upper - 1);
+    (lower, upper)
+}
+
+/// Run effects on their lights until they are stopped.
+///
+/// All effects share one scheduler and rate budget, and are tuned by
+/// the given commands, e.g. from the terminal or a reloaded preset file.
+/// The lights are left as the effects decide once they stop.
+///
+/// Returns the reason the effects stopped.
+pub fn run_effects(
+    setups: Vec<EffectSetup>,
+    home: &HueHome,
+    control: &EffectControl,
+    options: &RunOptions,
+    commands: Vec<Receiver<TuneCommand>>,
+) -> StopReason {
+    let mut scheduler = Scheduler::new(home, options);
+    for setup in setups {
+        scheduler.add_effect(setup);
+    }
+    for commands in commands {
+        scheduler.listen(commands);
+    }
+    let reason = scheduler.run(control);
+    scheduler.remove_all();
+    reason
+}
+
+/// Restore or fade out the lights after the effects stopped.
+///
+/// Lights that were switched off elsewhere are left off.
+pub fn leave_lights(
+    home: &HueHome,
+    snapshot: &LightSnapshot,
+    lights: &[String],
+    options: &RunOptions,
+    reason: StopReason,
+) -> crate::errors::Result<()> {
+    if reason == StopReason::LightsOff {
+        println!("Leaving the lights off.");
+        return Ok(());
+    }
+    match options.end {
+        EndAction::Restore => {
+            println!("Restoring lights...");
+            snapshot
+                .restore(home, options.end_tt)
+                .chain_err(|| "Unable to restore lights.")
+        }
+        EndAction::Off => {
+            println!("Fading out lights...");
+            fade_out(home, lights, options.end_tt).chain_err(|| "Unable to fade out lights.")
+        }
+    }
+}
+
+/// Fade the given lights to off.
+///
+/// All lights are faded out, even if some of them fail.
+/// The first error is returned.
+pub fn fade_out(home: &HueHome, lights: &[String], transition_time: u16) -> Result<(), HueError> {
+    let mut result = Ok(());
+    for uid in lights {
+        let light = match home.light(uid) {
+            Some(light) => light,
+            None => continue,
+        };
+        let mut batch = HueLightBatch::new(light.get_id());
+        batch.on(false);
+        batch.transition_time(transition_time);
+        let (_, params) = batch.build();
+        if let Err(err) = home.set_light_state(uid, &params) {
+            if result.is_ok() {
+                result = Err(err);
+            }
+        }
+    }
+    result
+}
+
+/// Last known state of the lights driven by an effect.
+///
+/// Only attributes that actually change are sent to the bridge,
+/// which keeps effects well below the bridge rate limit. Changes that
+/// did not reach the bridge are retried with the next batch of the light,
+/// changes the bridge rejected are dropped. Requests are not retried by
+/// the client, so an unreachable bridge does not hold up the others.
+pub struct LightStates {
+    states: HashMap<String, HueLightState>,
+    failed: HashMap<String, HueLightBatch>,
+}
+
+impl LightStates {
+
+    /// Capture the state of the given lights from the last refresh.
+    pub fn new(home: &HueHome, lights: &[String]) -> Self {
+        let states = lights
+            .iter()
+            .filter_map(|uid| Some((uid.clone(), home.light(uid)?.get_state().clone())))
+            .collect();
+        Self { states, failed: HashMap::new() }
+    }
+
+    /// Get the last known state of a light.
+    pub fn get(&self, uid: &str) -> Option<&HueLightState> {
+        self.states.get(uid)
+    }
+
+    /// Send the attributes of a batch that change the state of a light.
+    ///
+    /// Returns whether anything was sent.
+    pub fn send(&mut self, home: &HueHome, uid: &str, batch: &HueLightBatch) -> Result<bool, HueError> {
+        let state = self
+            .states
+            .get_mut(uid)
+            .ok_or_else(|| HueError::UnknownResource(uid.to_string()))?;
+
+        // Retry the changes of a failed batch, unless the new batch overrides them
+        let batch = match self.failed.remove(uid) {
+            Some(mut failed) => {
+                failed.merge(batch);
+                failed
+            }
+            None => batch.clone(),
+        };
+        let changes = state.minimize(&batch);
+        if changes.is_empty() {
+            return Ok(false);
+        }
+        let (_, params) = changes.build();
+        if let Err(err) = home.set_light_state_once(uid, &params) {
+            if err.is_transport() {
+                self.failed.insert(uid.to_string(), batch);
+            }
+            return Err(err);
+        }
+        state.apply(&batch);
+        Ok(true)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::errors::*;
+use super::{find_effect, EffectInfo, Params};
+
+/// Assignment of an effect to a set of lights.
+///
+/// Written as `<target>=<effect>[:<param>=<value>,...]`, e.g.
+/// `living=fireplace` or `bar=fireplace:preset=calm,sp=0`.
+pub struct Assignment {
+
+    /// Groups or lights driven by the effect
+    pub target: String,
+
+    /// The assigned effect
+    pub effect: &'static EffectInfo,
+
+    /// Parameter settings, by name or flag
+    pub settings: Vec<(String, String)>,
+}
+
+impl Assignment {
+
+    /// Parse an assignment.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts = text.splitn(2, '=');
+        let target = parts.next().unwrap_or_default().trim();
+        let spec = parts
+            .next()
+            .chain_err(|| format!("Invalid assignment '{}', expected e.g. living=fireplace.", text))?;
+        if target.is_empty() {
+            bail!("Invalid assignment '{}', expected e.g. living=fireplace.", text);
+        }
+
+        // Split the effect from its settings
+        let mut parts = spec.splitn(2, ':');
+        let name = parts.next().unwrap_or_default().trim();
+        let effect = find_effect(name).chain_err(|| format!("Unknown effect '{}'.", name))?;
+        let mut settings = Vec::new();
+        for setting in parts.next().unwrap_or_default().split(',').filter(|s| !s.trim().is_empty()) {
+            let mut kv = setting.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some(key), Some(val)) => settings.push((key.trim().to_string(), val.trim().to_string())),
+                _ => bail!("Invalid setting '{}' in assignment '{}'.", setting, text),
+            }
+        }
+        Ok(Self { target: target.to_string(), effect, settings })
+    }
+
+    /// Get the preset named in the settings.
+    pub fn preset(&self) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|(key, _)| key == "preset")
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Get the parameters given explicitly in the settings.
+    pub fn overrides(&self) -> Result<Params> {
+        let mut params = Params::new(self.effect.params);
+        for (key, val) in self.settings.iter().filter(|(key, _)| key != "preset") {
+            params
+                .set(key, val)
+                .chain_err(|| format!("Invalid settings for {} on '{}'.", self.effect.name, self.target))?;
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_assignments() {
+        let assignment = Assignment::parse("living=fireplace").unwrap();
+        assert_eq!(assignment.target, "living");
+        assert_eq!(assignment.effect.name, "fireplace");
+        assert!(assignment.settings.is_empty());
+        assert_eq!(assignment.preset(), None);
+
+        // Targets may contain light IDs
+        let assignment = Assignment::parse("Bar*,001788FFFE2A3B4C/id:7 = colorsweep").unwrap();
+        assert_eq!(assignment.target, "Bar*,001788FFFE2A3B4C/id:7");
+        assert_eq!(assignment.effect.name, "colorsweep");
+    }
+
+    #[test]
+    fn parses_settings() {
+        let assignment = Assignment::parse("bar=fireplace:preset=calm, sp=0,bb=90").unwrap();
+        assert_eq!(assignment.preset(), Some("calm"));
+        let overrides = assignment.overrides().unwrap();
+        assert_eq!(overrides.get("spark_probability"), 0.0);
+        assert_eq!(overrides.get("brightness_base"), 90.0);
+
+        assert!(Assignment::parse("bar=fireplace:sp=2").unwrap().overrides().is_err());
+        assert!(Assignment::parse("bar=fireplace:sparks=1").unwrap().overrides().is_err());
+    }
+
+    /// Get the message of a failed parse.
+    fn parse_error(text: &str) -> String {
+        Assignment::parse(text).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn requires_a_target() {
+        assert_eq!(parse_error("fireplace"), "Invalid assignment 'fireplace', expected e.g. living=fireplace.");
+        assert_eq!(parse_error(" =fireplace"), "Invalid assignment ' =fireplace', expected e.g. living=fireplace.");
+    }
+
+    #[test]
+    fn reports_unknown_effects_and_malformed_settings() {
+        assert_eq!(parse_error("living=campfire"), "Unknown effect 'campfire'.");
+        assert_eq!(parse_error("living=fireplace:sp"), "Invalid setting 'sp' in assignment 'living=fireplace:sp'.");
+
+        // Empty settings are skipped
+        assert!(Assignment::parse("living=fireplace:,").unwrap().settings.is_empty());
+    }
+}
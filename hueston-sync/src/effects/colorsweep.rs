@@ -1,59 +1,200 @@
-use hueston::{HueBridgeClient, HueLight, HueLightBatch};
+use hueston::{HueLightBatch, HueLightState};
 use rand::prelude::*;
+use std::collections::HashSet;
 use std::time::Duration;
+use super::{bounds, Effect, EffectLight, Frame, ParamKind, ParamSpec, Params};
 
-pub struct ColorsweepEffect {}
+/// Number of distinct hue steps.
+const HUE_STEPS: u16 = u16::MAX / 100;
+
+/// Number of attempts to find a hue no other light uses in the round.
+const MAX_HUE_ATTEMPTS: usize = 32;
+
+pub struct ColorsweepEffect {
+
+    /// Hue of the current round, in hue steps
+    hue: u16,
+
+    /// Lights that were updated in the current round
+    round: HashSet<String>,
+
+    /// Hues used in the current round
+    used: HashSet<u16>,
+
+    /// Time between rounds, in multiples of 100ms
+    round_time: u64,
+
+    /// Range of the time between rounds, the upper bound is exclusive
+    round_time_range: (u8, u8),
+
+    /// Transition time of a color change
+    transition_time: u8,
+
+    /// Brightness range, the upper bound is exclusive
+    brightness: (u8, u8),
+
+    /// Saturation range, the upper bound is exclusive
+    saturation: (u8, u8),
+}
 
 impl ColorsweepEffect {
-    pub fn run(&self, client: &HueBridgeClient, lights: Vec<HueLight>) {
+
+    /// Parameters of the effect.
+    pub const PARAMS: &'static [ParamSpec] = &[
+        ParamSpec {
+            name: "round_time_base",
+            flag: "rb",
+            help: "Time between rounds base",
+            kind: ParamKind::BYTE,
+            default: 88.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "round_time_variance",
+            flag: "rv",
+            help: "Time between rounds variance",
+            kind: ParamKind::BYTE,
+            default: 12.0,
+            variance_of: Some("round_time_base"),
+        },
+        ParamSpec {
+            name: "transition_time",
+            flag: "tt",
+            help: "Transition time",
+            kind: ParamKind::BYTE,
+            default: 50.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "brightness_base",
+            flag: "bb",
+            help: "Brightness base",
+            kind: ParamKind::BRIGHTNESS,
+            default: 202.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "brightness_variance",
+            flag: "bv",
+            help: "Brightness variance",
+            kind: ParamKind::BYTE,
+            default: 52.0,
+            variance_of: Some("brightness_base"),
+        },
+        ParamSpec {
+            name: "saturation_base",
+            flag: "sb",
+            help: "Saturation base",
+            kind: ParamKind::SATURATION,
+            default: 214.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "saturation_variance",
+            flag: "sv",
+            help: "Saturation variance",
+            kind: ParamKind::BYTE,
+            default: 40.0,
+            variance_of: Some("saturation_base"),
+        },
+    ];
+
+    /// Construct the effect from its parameters.
+    pub fn from_params(params: &Params) -> Self {
+        let mut effect = Self {
+            hue: thread_rng().gen_range(0, HUE_STEPS),
+            round: HashSet::new(),
+            used: HashSet::new(),
+            round_time: 0,
+            round_time_range: (0, 1),
+            transition_time: 0,
+            brightness: (1, 2),
+            saturation: (0, 1),
+        };
+        effect.update(params);
+        effect.round_time = effect.gen_round_time();
+        effect
+    }
+
+    /// Pick a random time between rounds.
+    fn gen_round_time(&self) -> u64 {
+        let (min, max) = self.round_time_range;
+        u64::from(thread_rng().gen_range(min, max))
+    }
+
+    /// Start a new round with a shifted hue.
+    fn next_round(&mut self) {
         let mut rng = thread_rng();
+        self.hue = self.hue.wrapping_add(rng.gen_range(HUE_STEPS / 8, HUE_STEPS / 4));
+        while self.hue > HUE_STEPS {
+            self.hue -= HUE_STEPS;
+        }
+        self.round.clear();
+        self.used.clear();
+        self.round_time = self.gen_round_time();
+    }
+}
+
+impl Effect for ColorsweepEffect {
+    fn init(&mut self, light: &EffectLight, state: &HueLightState) -> Option<HueLightBatch> {
 
-        // Pick a random hue to start with
-        let mut hue: u16 = rng.gen_range(0, std::u16::MAX / 100);
-
-        // Turn all lights on
-        for (i, _) in lights.iter().filter(|light| !light.is_on()).enumerate() {
-            let (light_i, params) = {
-                let mut batch = HueLightBatch::new(i + 1);
-                batch.on(true);
-                batch.brightness(1);
-                batch.saturation(1);
-                batch.transition_time(1);
-                batch.build()
-            };
-            client.set_light_state(light_i, &params);
+        // Turn the light on
+        if state.is_on() {
+            return None;
         }
+        let mut batch = HueLightBatch::new(light.id);
+        batch.on(true);
+        batch.brightness(1);
+        batch.saturation(1);
+        batch.transition_time(1);
+        Some(batch)
+    }
 
-        loop {
-            let tt: u64 = rng.gen_range(75, 101);
-            let mut set = std::collections::HashSet::new();
-            for (i, _) in lights.iter().enumerate() {
-                let mut batch = HueLightBatch::new(i + 1);
-                let hue_mod = rng.gen_range(std::u16::MAX / 100 / 32, std::u16::MAX / 100 / 20);
-                let mut get_hue = || -> u16 {
-                    if rng.gen_bool(0.5) {
-                        hue.wrapping_add(hue_mod)
-                    } else {
-                        hue.wrapping_sub(hue_mod)
-                    }
-                };
-                let mut hue = get_hue();
-                while !set.insert(hue) {
-                    hue = get_hue();
-                }
-                hue = hue.wrapping_mul(100);
-                batch.hue(hue);
-                batch.saturation(rng.gen_range(175, 255));
-                batch.brightness(rng.gen_range(150, 255));
-                batch.transition_time(50);
-                let (light_i, params) = batch.build();
-                client.set_light_state(light_i, &params);
+    fn tick(&mut self, light: &EffectLight) -> Frame {
+        let mut rng = thread_rng();
+
+        // A light that was already updated starts the next round
+        if !self.round.insert(light.uid.clone()) {
+            self.next_round();
+            self.round.insert(light.uid.clone());
+        }
+
+        // Pick a hue close to the round hue, but different for every light.
+        // The spread grows with the lights in the round, so there are
+        // always unused hues left.
+        let base = self.hue;
+        let spread = HUE_STEPS / 20 + self.round.len() as u16;
+        let mut get_hue = || -> u16 {
+            let hue_mod = rng.gen_range(HUE_STEPS / 32, spread);
+            if rng.gen_bool(0.5) {
+                base.wrapping_add(hue_mod)
+            } else {
+                base.wrapping_sub(hue_mod)
             }
-            hue = hue.wrapping_add(rng.gen_range(std::u16::MAX / 100 / 8, std::u16::MAX / 100 / 4));
-            while hue > std::u16::MAX / 100 {
-                hue -= std::u16::MAX / 100;
+        };
+        let mut hue = get_hue();
+        for _ in 0..MAX_HUE_ATTEMPTS {
+            if self.used.insert(hue) {
+                break;
             }
-            std::thread::sleep(Duration::from_millis(tt * 100));
+            hue = get_hue();
         }
+
+        let mut batch = HueLightBatch::new(light.id);
+        batch.hue(hue.wrapping_mul(100));
+        batch.saturation(rng.gen_range(self.saturation.0, self.saturation.1));
+        batch.brightness(rng.gen_range(self.brightness.0, self.brightness.1));
+        batch.transition_time(u16::from(self.transition_time));
+        Frame {
+            batch: Some(batch),
+            delay: Duration::from_millis(self.round_time * 100),
+        }
+    }
+
+    fn update(&mut self, params: &Params) {
+        self.round_time_range = bounds(params.get_u8("round_time_base"), params.get_u8("round_time_variance"), 1);
+        self.transition_time = params.get_u8("transition_time");
+        self.brightness = bounds(params.get_u8("brightness_base"), params.get_u8("brightness_variance"), 1);
+        self.saturation = bounds(params.get_u8("saturation_base"), params.get_u8("saturation_variance"), 0);
     }
 }
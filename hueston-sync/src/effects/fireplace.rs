@@ -1,6 +1,7 @@
-use hueston::{HueBridgeClient, HueLight, HueLightBatch};
+use hueston::{HueLightBatch, HueLightState};
 use rand::prelude::*;
 use std::time::Duration;
+use super::{bounds, Effect, EffectLight, Frame, ParamKind, ParamSpec, Params};
 
 pub struct FireplaceSimulation {
     pub brightness_base: u8,
@@ -18,131 +19,176 @@ pub struct FireplaceSimulation {
 
 impl Default for FireplaceSimulation {
     fn default() -> Self {
-        Self {
-            brightness_base: 70,
-            brightness_variance: 10,
-            brightness_spark_base: 110,
-            brightness_spark_variance: 10,
-            saturation_base: 247,
-            saturation_variance: 7,
-            saturation_spark_base: 227,
-            saturation_spark_variance: 27,
-            transition_time_base: 3,
-            transition_time_variance: 2,
-            spark_probability: 0.01,
-        }
+        Self::from_params(&Params::new(Self::PARAMS))
     }
 }
 
 impl FireplaceSimulation {
-    pub fn run(&self, client: &HueBridgeClient, lights: Vec<HueLight>) {
-        let (send, recv) = std::sync::mpsc::channel();
 
-        // Calculate boundaries
-        let brightness_min = self
-            .brightness_base
-            .saturating_sub(self.brightness_variance)
-            .max(1);
-        let brightness_max = self
-            .brightness_base
-            .saturating_add(self.brightness_variance.saturating_add(1))
-            .min(255);
-        let brightness_spark_min = self
-            .brightness_spark_base
-            .saturating_sub(self.brightness_spark_variance)
-            .max(1);
-        let brightness_spark_max = self
-            .brightness_spark_base
-            .saturating_add(self.brightness_spark_variance.saturating_add(1))
-            .min(255);
-        let saturation_min = self
-            .saturation_base
-            .saturating_sub(self.saturation_variance)
-            .max(1);
-        let saturation_max = self
-            .saturation_base
-            .saturating_add(self.saturation_variance.saturating_add(1))
-            .min(255);
-        let saturation_spark_min = self
-            .saturation_spark_base
-            .saturating_sub(self.saturation_spark_variance)
-            .max(1);
-        let saturation_spark_max = self
-            .saturation_spark_base
-            .saturating_add(self.saturation_spark_variance.saturating_add(1))
-            .min(255);
-        let transition_min = self
-            .transition_time_base
-            .saturating_sub(self.transition_time_variance)
-            .max(0);
-        let transition_max = self
-            .transition_time_base
-            .saturating_add(self.transition_time_variance.saturating_add(1))
-            .min(255);
-        let spark_probability = self.spark_probability.min(1.0).max(0.0);
+    /// Parameters of the simulation.
+    pub const PARAMS: &'static [ParamSpec] = &[
+        ParamSpec {
+            name: "brightness_base",
+            flag: "bb",
+            help: "Brightness base",
+            kind: ParamKind::BRIGHTNESS,
+            default: 70.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "brightness_variance",
+            flag: "bv",
+            help: "Brightness variance",
+            kind: ParamKind::BYTE,
+            default: 10.0,
+            variance_of: Some("brightness_base"),
+        },
+        ParamSpec {
+            name: "saturation_base",
+            flag: "sb",
+            help: "Saturation base",
+            kind: ParamKind::SATURATION,
+            default: 247.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "saturation_variance",
+            flag: "sv",
+            help: "Saturation variance",
+            kind: ParamKind::BYTE,
+            default: 7.0,
+            variance_of: Some("saturation_base"),
+        },
+        ParamSpec {
+            name: "transition_time_base",
+            flag: "tb",
+            help: "Transition time base",
+            kind: ParamKind::BYTE,
+            default: 3.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "transition_time_variance",
+            flag: "tv",
+            help: "Transition time variance",
+            kind: ParamKind::BYTE,
+            default: 2.0,
+            variance_of: Some("transition_time_base"),
+        },
+        ParamSpec {
+            name: "brightness_spark_base",
+            flag: "sbb",
+            help: "Spark brightness base",
+            kind: ParamKind::BRIGHTNESS,
+            default: 110.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "brightness_spark_variance",
+            flag: "sbv",
+            help: "Spark brightness variance",
+            kind: ParamKind::BYTE,
+            default: 10.0,
+            variance_of: Some("brightness_spark_base"),
+        },
+        ParamSpec {
+            name: "saturation_spark_base",
+            flag: "ssb",
+            help: "Spark saturation base",
+            kind: ParamKind::SATURATION,
+            default: 227.0,
+            variance_of: None,
+        },
+        ParamSpec {
+            name: "saturation_spark_variance",
+            flag: "ssv",
+            help: "Spark saturation variance",
+            kind: ParamKind::BYTE,
+            default: 27.0,
+            variance_of: Some("saturation_spark_base"),
+        },
+        ParamSpec {
+            name: "spark_probability",
+            flag: "sp",
+            help: "Spark probability",
+            kind: ParamKind::PROBABILITY,
+            default: 0.01,
+            variance_of: None,
+        },
+    ];
 
-        // Iterate over all lights
-        for (i, light) in lights.iter().enumerate() {
-            let send = send.clone();
+    /// Construct the simulation from its parameters.
+    pub fn from_params(params: &Params) -> Self {
+        Self {
+            brightness_base: params.get_u8("brightness_base"),
+            brightness_variance: params.get_u8("brightness_variance"),
+            brightness_spark_base: params.get_u8("brightness_spark_base"),
+            brightness_spark_variance: params.get_u8("brightness_spark_variance"),
+            saturation_base: params.get_u8("saturation_base"),
+            saturation_variance: params.get_u8("saturation_variance"),
+            saturation_spark_base: params.get_u8("saturation_spark_base"),
+            saturation_spark_variance: params.get_u8("saturation_spark_variance"),
+            transition_time_base: params.get_u8("transition_time_base"),
+            transition_time_variance: params.get_u8("transition_time_variance"),
+            spark_probability: params.get("spark_probability"),
+        }
+    }
+}
 
-            // Turn the light on
-            if !light.is_on() {
-                let (light_i, params) = {
-                    let mut batch = HueLightBatch::new(i + 1);
-                    batch.on(true);
-                    batch.build()
-                };
-                client.set_light_state(light_i, &params);
-            }
+impl Effect for FireplaceSimulation {
+    fn init(&mut self, light: &EffectLight, state: &HueLightState) -> Option<HueLightBatch> {
 
-            // Spawn a handling thread for the current light
-            std::thread::spawn(move || {
-                let mut rng = rand::thread_rng();
-                loop {
-                    let spark = rng.gen_bool(spark_probability);
-                    let tt: u16 = if spark {
-                        0
-                    } else {
-                        u16::from(rng.gen_range(transition_min, transition_max))
-                    };
+        // Turn the light on
+        if state.is_on() {
+            return None;
+        }
+        let mut batch = HueLightBatch::new(light.id);
+        batch.on(true);
+        Some(batch)
+    }
 
-                    // Prepare the command batch
-                    let batch = {
-                        let mut batch = HueLightBatch::new(i + 1);
-                        if spark {
-                            batch.hue(rng.gen_range(0, 80) * 100);
-                            batch.saturation(
-                                rng.gen_range(saturation_spark_min, saturation_spark_max),
-                            );
-                            batch.brightness(
-                                rng.gen_range(brightness_spark_min, brightness_spark_max),
-                            );
-                        } else {
-                            batch.hue(rng.gen_range(20, 70) * 100);
-                            batch.saturation(rng.gen_range(saturation_min, saturation_max));
-                            batch.brightness(rng.gen_range(brightness_min, brightness_max));
-                        }
-                        batch.transition_time(tt);
-                        batch
-                    };
+    fn tick(&mut self, light: &EffectLight) -> Frame {
+        let mut rng = rand::thread_rng();
 
-                    // Serialize the parameters
-                    let (light_i, params) = batch.build();
-                    let json = serde_json::to_string(&params).unwrap();
+        // Calculate boundaries
+        let (brightness_min, brightness_max) = bounds(self.brightness_base, self.brightness_variance, 1);
+        let (brightness_spark_min, brightness_spark_max) =
+            bounds(self.brightness_spark_base, self.brightness_spark_variance, 1);
+        let (saturation_min, saturation_max) = bounds(self.saturation_base, self.saturation_variance, 1);
+        let (saturation_spark_min, saturation_spark_max) =
+            bounds(self.saturation_spark_base, self.saturation_spark_variance, 1);
+        let (transition_min, transition_max) =
+            bounds(self.transition_time_base, self.transition_time_variance, 0);
+        let spark_probability = self.spark_probability.clamp(0.0, 1.0);
 
-                    // Send the parameters through the channel
-                    send.send((light_i, json)).unwrap();
+        let spark = rng.gen_bool(spark_probability);
+        let tt: u16 = if spark {
+            0
+        } else {
+            u16::from(rng.gen_range(transition_min, transition_max))
+        };
 
-                    // Sleep for the transition time
-                    let dur = Duration::from_millis(u64::from(tt) * 100);
-                    std::thread::sleep(dur);
-                }
-            });
+        // Prepare the command batch
+        let mut batch = HueLightBatch::new(light.id);
+        if spark {
+            batch.hue(rng.gen_range(0, 80) * 100);
+            batch.saturation(rng.gen_range(saturation_spark_min, saturation_spark_max));
+            batch.brightness(rng.gen_range(brightness_spark_min, brightness_spark_max));
+        } else {
+            batch.hue(rng.gen_range(20, 70) * 100);
+            batch.saturation(rng.gen_range(saturation_min, saturation_max));
+            batch.brightness(rng.gen_range(brightness_min, brightness_max));
         }
+        batch.transition_time(tt);
 
-        // Receive parameters
-        while let Ok((light_i, params)) = recv.recv() {
-            client.set_light_state_str(light_i, &params);
+        // Wait for the transition to finish
+        Frame {
+            batch: Some(batch),
+            delay: Duration::from_millis(u64::from(tt) * 100),
         }
     }
+
+    fn update(&mut self, params: &Params) {
+        *self = Self::from_params(params);
+    }
 }
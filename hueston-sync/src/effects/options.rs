@@ -0,0 +1,235 @@
+use chrono::{Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use clap::{Arg, ArgMatches};
+use std::time::{Duration, Instant};
+
+use crate::errors::*;
+use super::DEFAULT_RATE;
+
+/// What happens to the lights when an effect ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndAction {
+
+    /// Restore the state from before the effect started.
+    Restore,
+
+    /// Fade all lights to off.
+    Off,
+}
+
+/// Options for running effects.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+
+    /// Commands per second and bridge
+    pub rate: f64,
+
+    /// Time at which the effects stop on their own
+    pub deadline: Option<Instant>,
+
+    /// Whether to stop once all lights were switched off elsewhere
+    pub stop_when_lights_off: bool,
+
+    /// What happens to the lights afterwards
+    pub end: EndAction,
+
+    /// Transition time for leaving the lights, in multiples of 100ms
+    pub end_tt: u16,
+
+    /// Whether to only print the effective parameters
+    pub show_params: bool,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            rate: DEFAULT_RATE,
+            deadline: None,
+            stop_when_lights_off: false,
+            end: EndAction::Restore,
+            end_tt: 10,
+            show_params: false,
+        }
+    }
+}
+
+/// Build the command-line arguments for running effects.
+pub fn run_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("restore_tt")
+            .long("rt")
+            .takes_value(true)
+            .help("Transition time for restoring or fading out the lights afterwards"),
+        Arg::with_name("end")
+            .long("end")
+            .takes_value(true)
+            .possible_values(&["restore", "off"])
+            .help("Restore the lights afterwards or fade them to off"),
+        Arg::with_name("duration")
+            .long("duration")
+            .takes_value(true)
+            .help("Stop the effects after a duration, e.g. 45m or 1h30m"),
+        Arg::with_name("until")
+            .long("until")
+            .takes_value(true)
+            .help("Stop the effects at a local time, e.g. 23:00"),
+        Arg::with_name("stop_when_lights_off")
+            .long("stop-when-lights-off")
+            .help("Stop the effects and leave the lights off once they were switched off"),
+        Arg::with_name("rate")
+            .long("rate")
+            .takes_value(true)
+            .help("Maximum number of commands per second and bridge"),
+        Arg::with_name("show_params")
+            .long("show-params")
+            .help("Print the effective parameters instead of running the effects"),
+    ]
+}
+
+impl RunOptions {
+
+    /// Parse the options from command-line arguments.
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let mut options = Self::default();
+        if let Some(val) = matches.value_of("rate") {
+            options.rate = val.parse().chain_err(|| "Invalid rate.")?;
+        }
+        if let Some(val) = matches.value_of("restore_tt") {
+            options.end_tt = val.parse().chain_err(|| "Invalid restore transition time.")?;
+        }
+        if let Some(val) = matches.value_of("end") {
+            options.end = match val {
+                "restore" => EndAction::Restore,
+                "off" => EndAction::Off,
+                _ => bail!("Invalid end action '{}'.", val),
+            };
+        }
+        options.stop_when_lights_off = matches.is_present("stop_when_lights_off");
+        options.show_params = matches.is_present("show_params");
+
+        // The effects stop at the earliest deadline
+        let now = Instant::now();
+        let mut deadlines = Vec::new();
+        if let Some(val) = matches.value_of("duration") {
+            let deadline = now
+                .checked_add(parse_duration(val)?)
+                .chain_err(|| format!("Duration '{}' is too long.", val))?;
+            deadlines.push(deadline);
+        }
+        if let Some(val) = matches.value_of("until") {
+            let deadline = now
+                .checked_add(parse_until(val)?)
+                .chain_err(|| format!("Invalid time '{}'.", val))?;
+            deadlines.push(deadline);
+        }
+        options.deadline = deadlines.into_iter().min();
+        Ok(options)
+    }
+}
+
+/// Parse a duration like `45m`, `1h30m` or `90s`.
+///
+/// A plain number is taken as seconds. Zero durations are rejected.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    if let Ok(secs) = text.parse::<u64>() {
+        if secs == 0 {
+            bail!("Invalid duration '{}', expected e.g. 45m or 1h30m.", text);
+        }
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => bail!("Invalid duration '{}', expected e.g. 45m or 1h30m.", text),
+        };
+        let value: u64 = number
+            .parse()
+            .chain_err(|| format!("Invalid duration '{}', expected e.g. 45m or 1h30m.", text))?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .chain_err(|| format!("Duration '{}' is too long.", text))?;
+        number.clear();
+    }
+    if !number.is_empty() || total == 0 {
+        bail!("Invalid duration '{}', expected e.g. 45m or 1h30m.", text);
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Get the time until the next occurrence of a local time like `23:00`.
+pub fn parse_until(text: &str) -> Result<Duration> {
+    let time = NaiveTime::parse_from_str(text.trim(), "%H:%M")
+        .chain_err(|| format!("Invalid time '{}', expected e.g. 23:00.", text))?;
+    let now = Local::now();
+    let mut until = Local
+        .from_local_datetime(&now.date_naive().and_time(time))
+        .earliest()
+        .chain_err(|| format!("Invalid local time '{}'.", text))?;
+    if until <= now {
+        until += ChronoDuration::days(1);
+    }
+    (until - now)
+        .to_std()
+        .chain_err(|| format!("Invalid time '{}'.", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("45m").unwrap(), Duration::from_secs(45 * 60));
+        assert_eq!(parse_duration(" 1h30m ").unwrap(), Duration::from_secs(90 * 60));
+        assert_eq!(parse_duration("1h2m3s").unwrap(), Duration::from_secs(3723));
+    }
+
+    #[test]
+    fn requires_a_unit_after_every_number() {
+        assert_eq!(
+            parse_duration("1h30").unwrap_err().to_string(),
+            "Invalid duration '1h30', expected e.g. 45m or 1h30m."
+        );
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("45 m").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn rejects_zero_durations() {
+        // The effects would stop right away
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("0h0m").is_err());
+        assert_eq!(parse_duration("0h1s").unwrap(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration("99999999999999999h").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn parses_times_of_day() {
+        let until = parse_until("23:00").unwrap();
+        assert!(until > Duration::from_secs(0));
+
+        // Days with a daylight saving change have 25 hours
+        assert!(until <= Duration::from_secs(25 * 3600));
+        assert_eq!(parse_until("7pm").unwrap_err().to_string(), "Invalid time '7pm', expected e.g. 23:00.");
+        assert!(parse_until("24:00").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use clap::{Arg, ArgMatches};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::errors::*;
+
+/// Type and valid range of an effect parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+
+    /// Whole number within an inclusive range
+    Integer { min: i64, max: i64 },
+
+    /// Decimal number within an inclusive range
+    Decimal { min: f64, max: f64 },
+}
+
+impl ParamKind {
+
+    /// A byte, e.g. a transition time in multiples of 100ms.
+    pub const BYTE: ParamKind = ParamKind::Integer { min: 0, max: 255 };
+
+    /// A brightness as accepted by the lights.
+    pub const BRIGHTNESS: ParamKind = ParamKind::Integer { min: 1, max: 254 };
+
+    /// A saturation as accepted by the lights.
+    pub const SATURATION: ParamKind = ParamKind::Integer { min: 0, max: 254 };
+
+    /// A probability.
+    pub const PROBABILITY: ParamKind = ParamKind::Decimal { min: 0.0, max: 1.0 };
+
+    /// Get the inclusive range of valid values.
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            ParamKind::Integer { min, max } => (min as f64, max as f64),
+            ParamKind::Decimal { min, max } => (min, max),
+        }
+    }
+
+    /// Check a value against the type and range.
+    ///
+    /// Returns the reason if the value is invalid.
+    pub fn check(self, val: f64) -> std::result::Result<f64, String> {
+        let (min, max) = self.range();
+        match self {
+            ParamKind::Integer { .. } if !val.is_finite() || val.fract() != 0.0 || val < min || val > max => {
+                Err(format!("expected a whole number from {} to {}", min, max))
+            }
+            ParamKind::Decimal { .. } if !val.is_finite() || val < min || val > max => {
+                Err(format!("expected a number from {} to {}", min, max))
+            }
+            _ => Ok(val),
+        }
+    }
+}
+
+/// Description of an effect parameter.
+pub struct ParamSpec {
+
+    /// Parameter name
+    pub name: &'static str,
+
+    /// Long command-line flag, without dashes
+    pub flag: &'static str,
+
+    /// Help text
+    pub help: &'static str,
+
+    /// Type and valid range
+    pub kind: ParamKind,
+
+    /// Default value
+    pub default: f64,
+
+    /// Name of the base parameter, if this is a variance around it
+    pub variance_of: Option<&'static str>,
+}
+
+impl ParamSpec {
+
+    /// Build the command-line argument for the parameter.
+    pub fn to_arg(&self) -> Arg<'static, 'static> {
+        Arg::with_name(self.name)
+            .long(self.flag)
+            .takes_value(true)
+            .help(self.help)
+    }
+
+    /// Parse and check a value given as text.
+    pub fn parse(&self, val: &str) -> Result<f64> {
+        val.trim()
+            .parse()
+            .map_err(|_| "expected a number".to_string())
+            .and_then(|num| self.kind.check(num))
+            .map_err(|reason| format!("Invalid value '{}' for {} (--{}), {}.", val, self.name, self.flag, reason).into())
+    }
+
+    /// Check a value, e.g. from a preset.
+    pub fn check(&self, val: f64) -> Result<f64> {
+        self.kind
+            .check(val)
+            .map_err(|reason| format!("Invalid value {} for {}, {}.", val, self.name, reason).into())
+    }
+}
+
+/// Parameter values of an effect.
+///
+/// Parameters that were not given use the default of their spec.
+/// All values are checked against the type and range of their spec.
+#[derive(Clone)]
+pub struct Params {
+    specs: &'static [ParamSpec],
+    values: HashMap<&'static str, f64>,
+}
+
+impl Params {
+
+    /// Construct the default parameters.
+    pub fn new(specs: &'static [ParamSpec]) -> Self {
+        Self {
+            specs,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Override parameters given as command-line arguments.
+    pub fn apply_matches(&mut self, matches: &ArgMatches) -> Result<()> {
+        for spec in self.specs {
+            if let Some(val) = matches.value_of(spec.name) {
+                let val = spec.parse(val)?;
+                self.values.insert(spec.name, val);
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the spec of a parameter by name or flag.
+    pub fn spec(&self, name: &str) -> Option<&'static ParamSpec> {
+        self.specs.iter().find(|spec| spec.name == name || spec.flag == name)
+    }
+
+    /// Set a parameter by name or flag from text.
+    pub fn set(&mut self, name: &str, val: &str) -> Result<()> {
+        let spec = self.spec(name).chain_err(|| format!("Unknown parameter '{}'.", name))?;
+        let val = spec.parse(val)?;
+        self.values.insert(spec.name, val);
+        Ok(())
+    }
+
+    /// Set a parameter by name or flag.
+    pub fn set_value(&mut self, name: &str, val: f64) -> Result<()> {
+        let spec = self.spec(name).chain_err(|| format!("Unknown parameter '{}'.", name))?;
+        let val = spec.check(val)?;
+        self.values.insert(spec.name, val);
+        Ok(())
+    }
+
+    /// Get the value of a parameter.
+    ///
+    /// Panics if the effect does not declare the parameter.
+    pub fn get(&self, name: &str) -> f64 {
+        if let Some(val) = self.values.get(name) {
+            return *val;
+        }
+        self.specs
+            .iter()
+            .find(|spec| spec.name == name)
+            .map(|spec| spec.default)
+            .unwrap_or_else(|| panic!("Unknown parameter '{}'", name))
+    }
+
+    /// Get the value of a parameter as a byte.
+    pub fn get_u8(&self, name: &str) -> u8 {
+        self.get(name).round().clamp(0.0, 255.0) as u8
+    }
+
+    /// Take over the values that were set on other parameters.
+    pub fn merge(&mut self, other: &Params) {
+        for (name, val) in &other.values {
+            if self.spec(name).is_some() {
+                self.values.insert(name, *val);
+            }
+        }
+    }
+
+    /// Blend between two sets of parameters of the same effect.
+    ///
+    /// At `t = 0` the values of `from` are used, at `t = 1` those of `to`.
+    /// Whole numbers are rounded.
+    pub fn interpolate(from: &Params, to: &Params, t: f64) -> Params {
+        let t = t.clamp(0.0, 1.0);
+        let mut params = Params::new(to.specs);
+        for spec in to.specs {
+            let (a, b) = (from.get(spec.name), to.get(spec.name));
+            let val = a + (b - a) * t;
+            let val = match spec.kind {
+                ParamKind::Integer { .. } => val.round(),
+                ParamKind::Decimal { .. } => val,
+            };
+            params.values.insert(spec.name, val);
+        }
+        params
+    }
+
+    /// Describe variances that reach beyond the range of their base.
+    ///
+    /// Such values still work, but are clamped by the effect.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for spec in self.specs {
+            let base_spec = match spec.variance_of.and_then(|base| self.spec(base)) {
+                Some(base_spec) => base_spec,
+                None => continue,
+            };
+            let (base, variance) = (self.get(base_spec.name), self.get(spec.name));
+            let (min, max) = base_spec.kind.range();
+            if base - variance < min || base + variance > max {
+                warnings.push(format!(
+                    "{} {} around {} {} exceeds the valid range {} to {}, values will be clamped.",
+                    spec.name, variance, base_spec.name, base, min, max,
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for spec in self.specs {
+            let val = self.get(spec.name);
+            write!(f, "  {} (--{}) = {}", spec.name, spec.flag, val)?;
+            if (val - spec.default).abs() > f64::EPSILON {
+                write!(f, " (default {})", spec.default)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::FireplaceSimulation;
+    use clap::App;
+
+    fn fireplace() -> Params {
+        Params::new(FireplaceSimulation::PARAMS)
+    }
+
+    /// Get the message of a failed parse.
+    fn parse_error(spec: &ParamSpec, val: &str) -> String {
+        spec.parse(val).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn checks_whole_numbers_against_their_range() {
+        let spec = fireplace().spec("brightness_base").unwrap();
+        assert_eq!(spec.parse(" 254 ").unwrap(), 254.0);
+        assert_eq!(spec.parse("1").unwrap(), 1.0);
+        assert_eq!(
+            parse_error(spec, "255"),
+            "Invalid value '255' for brightness_base (--bb), expected a whole number from 1 to 254."
+        );
+
+        // Fractions would be rounded silently by the effect
+        assert_eq!(
+            parse_error(spec, "1.5"),
+            "Invalid value '1.5' for brightness_base (--bb), expected a whole number from 1 to 254."
+        );
+        assert_eq!(parse_error(spec, "bright"), "Invalid value 'bright' for brightness_base (--bb), expected a number.");
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_finite() {
+        let spec = fireplace().spec("spark_probability").unwrap();
+        assert_eq!(spec.parse("0.25").unwrap(), 0.25);
+        assert_eq!(spec.parse("1").unwrap(), 1.0);
+
+        // NaN parses as a number, but fails every range comparison
+        assert_eq!(
+            parse_error(spec, "NaN"),
+            "Invalid value 'NaN' for spark_probability (--sp), expected a number from 0 to 1."
+        );
+        assert_eq!(
+            spec.check(f64::NAN).unwrap_err().to_string(),
+            "Invalid value NaN for spark_probability, expected a number from 0 to 1."
+        );
+        assert!(spec.parse("-inf").is_err());
+    }
+
+    #[test]
+    fn maps_spark_flags() {
+        let args: Vec<Arg> = FireplaceSimulation::PARAMS.iter().map(ParamSpec::to_arg).collect();
+        let matches = App::new("fireplace")
+            .args(&args)
+            .get_matches_from(vec!["fireplace", "--sbb", "120", "--sbv", "5", "--ssb", "200", "--ssv", "20"]);
+        let mut params = fireplace();
+        params.apply_matches(&matches).unwrap();
+        assert_eq!(params.get("brightness_spark_base"), 120.0);
+        assert_eq!(params.get("brightness_spark_variance"), 5.0);
+        assert_eq!(params.get("saturation_spark_base"), 200.0);
+        assert_eq!(params.get("saturation_spark_variance"), 20.0);
+
+        // The regular flags are not affected
+        assert_eq!(params.get("brightness_base"), 70.0);
+        assert_eq!(params.get("saturation_base"), 247.0);
+    }
+
+    #[test]
+    fn looks_up_by_name_or_flag() {
+        let mut params = fireplace();
+        params.set("sp", "0.5").unwrap();
+        params.set("brightness_variance", "20").unwrap();
+        assert_eq!(params.get("spark_probability"), 0.5);
+        assert_eq!(params.get_u8("brightness_variance"), 20);
+        assert!(params.set("sparks", "1").is_err());
+        assert!(params.set_value("bb", 300.0).is_err());
+        assert_eq!(params.get("brightness_base"), 70.0);
+    }
+
+    #[test]
+    fn warns_about_variances_beyond_the_range() {
+        let mut params = fireplace();
+        assert!(params.warnings().is_empty());
+
+        params.set("sv", "20").unwrap();
+        let warnings = params.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("saturation_variance 20 around saturation_base 247"));
+
+        params.set("bb", "5").unwrap();
+        assert_eq!(params.warnings().len(), 2);
+    }
+
+    #[test]
+    fn interpolates_and_rounds() {
+        let from = fireplace();
+        let mut to = fireplace();
+        to.set("bb", "71").unwrap();
+        to.set("sp", "0.03").unwrap();
+
+        let half = Params::interpolate(&from, &to, 0.5);
+        assert_eq!(half.get("brightness_base"), 71.0);
+        assert!((half.get("spark_probability") - 0.02).abs() < 1e-9);
+        assert_eq!(half.get("saturation_base"), 247.0);
+
+        // The blend factor is clamped
+        assert_eq!(Params::interpolate(&from, &to, -1.0).get("brightness_base"), 70.0);
+        assert_eq!(Params::interpolate(&from, &to, 2.0).get("brightness_base"), 71.0);
+    }
+}
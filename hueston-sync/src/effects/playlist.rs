@@ -0,0 +1,264 @@
+use hueston::HueHome;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde_derive::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use crate::errors::*;
+use super::{
+    find_effect,
+    parse_duration,
+    EffectControl,
+    EffectInfo,
+    EffectSetup,
+    Params,
+    PresetFile,
+    RunOptions,
+    Scheduler,
+    StopReason,
+    TuneCommand,
+};
+
+/// Layout of a playlist entry in the playlist file.
+#[derive(Deserialize)]
+struct RawEntry {
+    effect: String,
+    preset: Option<String>,
+    duration: String,
+}
+
+/// Layout of the playlist file.
+#[derive(Deserialize)]
+struct RawPlaylist {
+    #[serde(default)]
+    shuffle: bool,
+    #[serde(default)]
+    crossfade: Option<String>,
+    #[serde(default = "default_repeat", rename = "loop")]
+    repeat: bool,
+    #[serde(default)]
+    entries: Vec<RawEntry>,
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+/// An effect played for some time.
+#[derive(Clone)]
+pub struct PlaylistEntry {
+
+    /// The effect to run
+    pub effect: &'static EffectInfo,
+
+    /// Preset of the effect
+    pub preset: Option<String>,
+
+    /// Time the effect runs
+    pub duration: Duration,
+}
+
+/// Effects that are run one after another.
+///
+/// Playlists are TOML files with a list of `[[entries]]`, each naming
+/// an effect, an optional preset and a duration like `45m`.
+#[derive(Clone)]
+pub struct Playlist {
+
+    /// Entries in the order of the file
+    pub entries: Vec<PlaylistEntry>,
+
+    /// Whether the entries are shuffled for every round
+    pub shuffle: bool,
+
+    /// Time for fading from one entry to the next
+    pub crossfade: Duration,
+
+    /// Whether the playlist starts over after the last entry
+    pub repeat: bool,
+}
+
+impl Playlist {
+
+    /// Load a playlist, checking it against the effects and presets.
+    pub fn load<P: AsRef<Path>>(path: P, presets: &PresetFile) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .chain_err(|| format!("Unable to read playlist {}.", path.display()))?;
+        Self::parse(&text, presets).chain_err(|| format!("Invalid playlist {}.", path.display()))
+    }
+
+    /// Parse a playlist from TOML.
+    pub fn parse(text: &str, presets: &PresetFile) -> Result<Self> {
+        let raw: RawPlaylist = toml::from_str(text).chain_err(|| "Unable to parse TOML.")?;
+        let mut entries = Vec::new();
+        for (i, entry) in raw.entries.into_iter().enumerate() {
+            let effect = find_effect(&entry.effect)
+                .chain_err(|| format!("Unknown effect '{}' in entry {}.", entry.effect, i + 1))?;
+            if let Some(preset) = &entry.preset {
+                presets.get(effect.name, preset)?;
+            }
+            let duration = parse_duration(&entry.duration)
+                .chain_err(|| format!("Invalid duration in entry {}.", i + 1))?;
+            entries.push(PlaylistEntry { effect, preset: entry.preset, duration });
+        }
+        if entries.is_empty() {
+            bail!("The playlist has no entries.");
+        }
+        let crossfade = match raw.crossfade {
+            Some(crossfade) => parse_duration(&crossfade).chain_err(|| "Invalid crossfade.")?,
+            None => Duration::from_secs(0),
+        };
+        Ok(Self { entries, shuffle: raw.shuffle, crossfade, repeat: raw.repeat })
+    }
+}
+
+/// Play a playlist on the given lights until it ends or is stopped.
+///
+/// All entries share one scheduler, so tuning commands and reloaded
+/// presets apply to whichever entry is running. Entries whose preset
+/// disappeared from the preset file are skipped.
+///
+/// Returns the reason the playlist stopped.
+pub fn run_playlist(
+    playlist: &Playlist,
+    presets: PresetFile,
+    home: &HueHome,
+    lights: &[String],
+    control: &EffectControl,
+    options: &RunOptions,
+    commands: Vec<Receiver<TuneCommand>>,
+) -> Result<StopReason> {
+    let mut scheduler = Scheduler::new(home, options);
+    scheduler.set_presets(presets);
+    for commands in commands {
+        scheduler.listen(commands);
+    }
+
+    // Crossfades are transition times in multiples of 100ms
+    let crossfade = playlist
+        .crossfade
+        .as_secs()
+        .saturating_mul(10)
+        .saturating_add(u64::from(playlist.crossfade.subsec_millis()) / 100);
+    let crossfade = Some(crossfade.min(u64::from(u16::MAX)) as u16).filter(|tt| *tt > 0);
+
+    let mut current = None;
+    loop {
+        let mut entries = playlist.entries.clone();
+        if playlist.shuffle {
+            entries.shuffle(&mut thread_rng());
+        }
+
+        let mut played = false;
+        for entry in &entries {
+            let setup = EffectSetup::new(
+                entry.effect,
+                entry.preset.as_deref(),
+                Params::new(entry.effect.params),
+                lights.to_vec(),
+                scheduler.get_presets(),
+            );
+            let setup = match setup {
+                Ok(setup) => setup,
+                Err(err) => {
+                    println!("[Effect {}] Skipping entry: {}", entry.effect.name, err);
+                    continue;
+                }
+            };
+            played = true;
+
+            // Replace the running effect
+            println!(
+                "[Effect {}] Playing{} for {}s",
+                entry.effect.name,
+                entry.preset.as_ref().map(|preset| format!(" preset '{}'", preset)).unwrap_or_default(),
+                entry.duration.as_secs(),
+            );
+            let fade_in = match current.take() {
+                Some(id) => {
+                    scheduler.remove_effect(id);
+                    crossfade
+                }
+                None => None,
+            };
+            current = Some(scheduler.add_effect_fading(setup, fade_in));
+
+            // Durations beyond the range of the clock play until stopped
+            let reason = scheduler.run_until(control, Instant::now().checked_add(entry.duration));
+            if reason != StopReason::Until {
+                scheduler.remove_all();
+                return Ok(reason);
+            }
+        }
+
+        if !played {
+            bail!("None of the playlist entries can be played.");
+        }
+        if !playlist.repeat {
+            break;
+        }
+    }
+    scheduler.remove_all();
+    Ok(StopReason::Finished)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presets() -> PresetFile {
+        PresetFile::parse(include_str!("../../../presets.toml")).unwrap()
+    }
+
+    #[test]
+    fn parses_example_playlist() {
+        let playlist = Playlist::parse(include_str!("../../../playlist.toml"), &presets()).unwrap();
+        assert!(!playlist.shuffle);
+        assert!(playlist.repeat);
+        assert_eq!(playlist.crossfade, Duration::from_secs(10));
+        assert_eq!(playlist.entries.len(), 3);
+        assert_eq!(playlist.entries[0].effect.name, "fireplace");
+        assert_eq!(playlist.entries[0].preset.as_deref(), Some("calm"));
+        assert_eq!(playlist.entries[0].duration, Duration::from_secs(2 * 3600));
+        assert_eq!(playlist.entries[2].preset, None);
+    }
+
+    #[test]
+    fn uses_defaults() {
+        let playlist = Playlist::parse("[[entries]]\neffect = \"colorsweep\"\nduration = \"5m\"\n", &presets()).unwrap();
+        assert!(!playlist.shuffle);
+        assert!(playlist.repeat);
+        assert_eq!(playlist.crossfade, Duration::from_secs(0));
+    }
+
+    /// Get the message of a failed parse.
+    fn parse_error(text: &str) -> String {
+        Playlist::parse(text, &presets()).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn requires_entries() {
+        assert_eq!(parse_error("shuffle = true\n"), "The playlist has no entries.");
+        assert_eq!(parse_error("[[entries]]\neffect = \"fireplace\"\n"), "Unable to parse TOML.");
+    }
+
+    #[test]
+    fn reports_the_invalid_entry() {
+        let text = "[[entries]]\neffect = \"colorsweep\"\nduration = \"5m\"\n\n[[entries]]\neffect = \"campfire\"\nduration = \"5m\"\n";
+        assert_eq!(parse_error(text), "Unknown effect 'campfire' in entry 2.");
+        assert_eq!(
+            parse_error("[[entries]]\neffect = \"fireplace\"\nduration = \"soon\"\n"),
+            "Invalid duration in entry 1."
+        );
+
+        // Presets are checked when the playlist is loaded, not when the entry plays
+        assert_eq!(
+            parse_error("[[entries]]\neffect = \"fireplace\"\npreset = \"gentle\"\nduration = \"5m\"\n"),
+            "Unknown preset 'gentle' for fireplace."
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+use crate::errors::*;
+use super::{find_effect, Params};
+
+/// Default location of the preset file.
+pub const DEFAULT_PRESET_FILE: &str = "presets.toml";
+
+/// Layout of the preset file.
+#[derive(Deserialize)]
+struct RawPresetFile {
+    #[serde(default)]
+    presets: BTreeMap<String, BTreeMap<String, BTreeMap<String, Value>>>,
+}
+
+/// Named parameter values of an effect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+
+    /// Parameter values, by parameter name
+    values: BTreeMap<&'static str, f64>,
+}
+
+impl Preset {
+
+    /// Get the parameter values.
+    pub fn get_values(&self) -> &BTreeMap<&'static str, f64> {
+        &self.values
+    }
+
+    /// Set the preset values on the parameters.
+    pub fn apply(&self, params: &mut Params) -> Result<()> {
+        for (name, val) in &self.values {
+            params.set_value(name, *val)?;
+        }
+        Ok(())
+    }
+}
+
+/// Effect presets loaded from a TOML file.
+///
+/// Presets are stored in tables named after the effect and preset,
+/// e.g. `[presets.fireplace.calm]`, and are validated against the
+/// parameters of the effect when loaded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PresetFile {
+
+    /// Presets by effect and preset name
+    presets: BTreeMap<String, BTreeMap<String, Preset>>,
+}
+
+impl PresetFile {
+
+    /// Load and validate a preset file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .chain_err(|| format!("Unable to read preset file {}.", path.display()))?;
+        Self::parse(&text).chain_err(|| format!("Invalid preset file {}.", path.display()))
+    }
+
+    /// Load a preset file if it exists.
+    pub fn load_if_exists<P: AsRef<Path>>(path: P) -> Result<Self> {
+        if path.as_ref().exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Parse and validate presets from TOML.
+    pub fn parse(text: &str) -> Result<Self> {
+        let raw: RawPresetFile = toml::from_str(text).chain_err(|| "Unable to parse TOML.")?;
+        let mut presets = BTreeMap::new();
+        for (effect_name, effect_presets) in raw.presets {
+            let info = find_effect(&effect_name)
+                .chain_err(|| format!("Unknown effect '{}' in presets.", effect_name))?;
+            let mut validated = BTreeMap::new();
+            for (preset_name, values) in effect_presets {
+                let location = format!("presets.{}.{}", effect_name, preset_name);
+                let params = Params::new(info.params);
+                let mut preset = Preset { values: BTreeMap::new() };
+                for (key, val) in values {
+                    let spec = params
+                        .spec(&key)
+                        .chain_err(|| format!("Unknown parameter '{}' in {}.", key, location))?;
+                    let val = match val {
+                        Value::Integer(val) => val as f64,
+                        Value::Float(val) => val,
+                        _ => bail!("Parameter '{}' in {} must be a number.", key, location),
+                    };
+                    let val = spec.check(val).chain_err(|| format!("Invalid parameter in {}.", location))?;
+                    preset.values.insert(spec.name, val);
+                }
+                validated.insert(preset_name, preset);
+            }
+            presets.insert(effect_name, validated);
+        }
+        Ok(Self { presets })
+    }
+
+    /// Get a preset of an effect.
+    pub fn get(&self, effect: &str, name: &str) -> Result<&Preset> {
+        self.presets
+            .get(effect)
+            .and_then(|presets| presets.get(name))
+            .chain_err(|| format!("Unknown preset '{}' for {}.", name, effect))
+    }
+
+    /// Iterate over the presets of all effects.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String, &Preset)> {
+        self.presets
+            .iter()
+            .flat_map(|(effect, presets)| presets.iter().map(move |(name, preset)| (effect, name, preset)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_shipped_presets() {
+        let presets = PresetFile::parse(include_str!("../../../presets.toml")).unwrap();
+        assert_eq!(presets.iter().count(), 3);
+
+        let mut params = Params::new(find_effect("fireplace").unwrap().params);
+        presets.get("fireplace", "calm").unwrap().apply(&mut params).unwrap();
+        assert_eq!(params.get("spark_probability"), 0.0);
+        assert_eq!(params.get("transition_time_base"), 4.0);
+        assert!(presets.get("fireplace", "gentle").is_err());
+        assert!(presets.get("colorsweep", "calm").is_err());
+    }
+
+    #[test]
+    fn stores_values_by_parameter_name() {
+        let presets = PresetFile::parse("[presets.fireplace.bright]\nbb = 200\nsp = 0.5\n").unwrap();
+        let values = presets.get("fireplace", "bright").unwrap().get_values();
+        assert_eq!(values.get("brightness_base"), Some(&200.0));
+        assert_eq!(values.get("spark_probability"), Some(&0.5));
+        assert_eq!(PresetFile::parse("").unwrap(), PresetFile::default());
+    }
+
+    /// Get the messages of a failed parse, outermost first.
+    fn parse_errors(text: &str) -> Vec<String> {
+        PresetFile::parse(text).unwrap_err().iter().map(|err| err.to_string()).collect()
+    }
+
+    #[test]
+    fn reports_where_presets_are_invalid() {
+        assert_eq!(parse_errors("[presets.campfire.calm]\nsp = 0\n")[0], "Unknown effect 'campfire' in presets.");
+        assert_eq!(
+            parse_errors("[presets.fireplace.calm]\nsparks = 0\n")[0],
+            "Unknown parameter 'sparks' in presets.fireplace.calm."
+        );
+        assert_eq!(
+            parse_errors("[presets.fireplace.calm]\nsp = \"none\"\n")[0],
+            "Parameter 'sp' in presets.fireplace.calm must be a number."
+        );
+    }
+
+    #[test]
+    fn checks_values_like_the_command_line() {
+        assert_eq!(
+            parse_errors("[presets.fireplace.calm]\nbb = 300\n"),
+            vec![
+                "Invalid parameter in presets.fireplace.calm.",
+                "Invalid value 300 for brightness_base, expected a whole number from 1 to 254.",
+            ]
+        );
+        assert!(PresetFile::parse("[presets.fireplace.calm]\nbb = 1.5\n").is_err());
+        assert!(PresetFile::parse("[presets.fireplace.calm]\nsp = nan\n").is_err());
+    }
+}
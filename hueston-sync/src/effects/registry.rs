@@ -0,0 +1,72 @@
+use clap::{App, AppSettings, Arg, SubCommand};
+
+use crate::selection::selection_args;
+use super::{run_args, ColorsweepEffect, Effect, FireplaceSimulation, ParamSpec, Params};
+
+/// Registered effect.
+pub struct EffectInfo {
+
+    /// Name as used on the command line
+    pub name: &'static str,
+
+    /// Short description
+    pub about: &'static str,
+
+    /// Parameters of the effect
+    pub params: &'static [ParamSpec],
+
+    /// Construct the effect from its parameters
+    pub create: fn(&Params) -> Box<dyn Effect>,
+}
+
+/// All known effects.
+///
+/// New effects only need to be added here to show up on the command line.
+pub static EFFECTS: &[EffectInfo] = &[
+    EffectInfo {
+        name: "fireplace",
+        about: "Flickering fireplace simulation",
+        params: FireplaceSimulation::PARAMS,
+        create: create_fireplace,
+    },
+    EffectInfo {
+        name: "colorsweep",
+        about: "Slowly sweeping colors",
+        params: ColorsweepEffect::PARAMS,
+        create: create_colorsweep,
+    },
+];
+
+fn create_fireplace(params: &Params) -> Box<dyn Effect> {
+    Box::new(FireplaceSimulation::from_params(params))
+}
+
+fn create_colorsweep(params: &Params) -> Box<dyn Effect> {
+    Box::new(ColorsweepEffect::from_params(params))
+}
+
+/// Find an effect by name.
+pub fn find_effect(name: &str) -> Option<&'static EffectInfo> {
+    EFFECTS.iter().find(|info| info.name == name)
+}
+
+/// Build the `simulate` subcommand with one subcommand per effect.
+pub fn simulate_command() -> App<'static, 'static> {
+    let effects = EFFECTS.iter().map(|info| {
+        SubCommand::with_name(info.name)
+            .about(info.about)
+            .arg(
+                Arg::with_name("preset")
+                    .long("preset")
+                    .takes_value(true)
+                    .help("Start from a preset, parameters given as flags take precedence"),
+            )
+            .args(&info.params.iter().map(ParamSpec::to_arg).collect::<Vec<_>>())
+    });
+    SubCommand::with_name("simulate")
+        .about("Run an effect on the lights")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .args(&selection_args())
+        .args(&run_args())
+        .subcommands(effects)
+}
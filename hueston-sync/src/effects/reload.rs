@@ -0,0 +1,51 @@
+use error_chain::ChainedError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::{PresetFile, TuneCommand};
+
+/// Time between checks whether the preset file changed.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Get the modification time of a file.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Watch a preset file and reload it when it changes.
+///
+/// Valid presets are sent to the `Scheduler`, which blends the running
+/// effects over to them. Invalid edits are reported and ignored, the
+/// effects keep running with their current parameters.
+pub fn spawn_preset_watcher(path: PathBuf) -> Receiver<TuneCommand> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let mut last_modified = modified(&path);
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let current = modified(&path);
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            // Reload the presets
+            match PresetFile::load(&path) {
+                Ok(presets) => {
+                    println!("Reloaded presets from {}", path.display());
+                    if sender.send(TuneCommand::Presets(presets)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    println!("Ignoring invalid presets, keeping the current parameters:");
+                    print!("{}", err.display_chain());
+                }
+            }
+        }
+    });
+    receiver
+}
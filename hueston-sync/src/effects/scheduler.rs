@@ -0,0 +1,646 @@
+use hueston::{HueHome, HueLightBatch};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{Adjustment, Effect, EffectLight, EffectSetup, LightStates, Params, PresetFile, RunOptions, TuneCommand};
+
+/// Longest time the scheduler sleeps without checking for a stop or pause.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time between checks whether the lights were switched off elsewhere.
+const LIGHTS_OFF_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time for blending over to reloaded presets.
+const RELOAD_FADE: Duration = Duration::from_secs(3);
+
+/// Default number of commands per second and bridge.
+///
+/// The bridge drops commands if lights are updated more often.
+pub const DEFAULT_RATE: f64 = 10.0;
+
+/// Shared handle for stopping and pausing running effects.
+#[derive(Debug, Clone)]
+pub struct EffectControl {
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+impl Default for EffectControl {
+    fn default() -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl EffectControl {
+
+    /// Construct a new `EffectControl` for running effects.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the effects.
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    /// Test whether the effects are still running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Pause or resume the effects.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    /// Test whether the effects are paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+/// Token bucket limiting the commands sent to each bridge.
+pub struct RateBudget {
+
+    /// Commands per second and bridge
+    rate: f64,
+
+    /// Available commands and time of the last refill, by bridge ID
+    buckets: HashMap<String, (f64, Instant)>,
+}
+
+impl RateBudget {
+
+    /// Construct a new `RateBudget` with the given commands per second and bridge.
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rate: rate.max(0.1),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Get the refilled bucket of a bridge.
+    fn bucket(&mut self, bridge: &str, now: Instant) -> &mut (f64, Instant) {
+        let rate = self.rate;
+        let bucket = self
+            .buckets
+            .entry(bridge.to_string())
+            .or_insert((rate, now));
+        let elapsed = now.duration_since(bucket.1);
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        bucket.0 = (bucket.0 + elapsed * rate).min(rate);
+        bucket.1 = now;
+        bucket
+    }
+
+    /// Get the time until a command can be sent to a bridge.
+    ///
+    /// Returns `None` if a command can be sent right away.
+    pub fn wait_time(&mut self, bridge: &str, now: Instant) -> Option<Duration> {
+        let rate = self.rate;
+        let (tokens, _) = *self.bucket(bridge, now);
+        if tokens >= 1.0 {
+            return None;
+        }
+        let secs = (1.0 - tokens) / rate;
+        Some(Duration::from_micros((secs * 1e6).ceil() as u64))
+    }
+
+    /// Account for a command sent to a bridge.
+    pub fn consume(&mut self, bridge: &str, now: Instant) {
+        self.bucket(bridge, now).0 -= 1.0;
+    }
+}
+
+/// Why a `Scheduler` stopped driving the effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+
+    /// The time passed to `Scheduler::run_until` was reached
+    Until,
+
+    /// The effects were stopped through their `EffectControl`
+    Stopped,
+
+    /// The deadline of the run options passed
+    Deadline,
+
+    /// All lights were switched off elsewhere
+    LightsOff,
+
+    /// No lights are left to drive
+    Finished,
+}
+
+/// Identifies an effect added to a `Scheduler`.
+///
+/// IDs are not reused, so the ID of a removed effect stays invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EffectId(usize);
+
+/// Parameters blending over to new values.
+struct Transition {
+    from: Params,
+    to: Params,
+    start: Instant,
+}
+
+/// An effect added to a `Scheduler`.
+struct Slot {
+    setup: EffectSetup,
+    effect: Box<dyn Effect>,
+    transition: Option<Transition>,
+}
+
+/// A light scheduled for ticks of an effect.
+struct Entry {
+    light: EffectLight,
+    bridge: String,
+    effect: EffectId,
+    fade_in: Option<u16>,
+}
+
+/// Central scheduler driving the ticks of all effects.
+///
+/// Every light has its own deadline for the next frame, the light with the
+/// earliest deadline is ticked next. All effects share one rate budget per
+/// bridge, lights are delayed until their bridge accepts commands again.
+///
+/// Removed effects and their lights are dropped, so effects can be
+/// replaced indefinitely, e.g. by a looping playlist.
+pub struct Scheduler<'a> {
+    home: &'a HueHome,
+    states: LightStates,
+    budget: RateBudget,
+    deadline: Option<Instant>,
+    lights_off_check: Option<Instant>,
+    commands: Vec<Receiver<TuneCommand>>,
+    presets: PresetFile,
+    effects: BTreeMap<EffectId, Slot>,
+    entries: BTreeMap<usize, Entry>,
+    next_effect: usize,
+    next_entry: usize,
+    queue: BinaryHeap<Reverse<(Instant, usize)>>,
+}
+
+impl<'a> Scheduler<'a> {
+
+    /// Construct a new `Scheduler` for the lights of a home.
+    pub fn new(home: &'a HueHome, options: &RunOptions) -> Self {
+        let lights: Vec<String> = home.lights().map(|(uid, _)| uid.clone()).collect();
+        let lights_off_check = if options.stop_when_lights_off {
+            Some(Instant::now() + LIGHTS_OFF_INTERVAL)
+        } else {
+            None
+        };
+        Self {
+            home,
+            states: LightStates::new(home, &lights),
+            budget: RateBudget::new(options.rate),
+            deadline: options.deadline,
+            lights_off_check,
+            commands: Vec::new(),
+            presets: PresetFile::default(),
+            effects: BTreeMap::new(),
+            entries: BTreeMap::new(),
+            next_effect: 0,
+            next_entry: 0,
+            queue: BinaryHeap::new(),
+        }
+    }
+
+    /// Apply tuning commands while running.
+    ///
+    /// Commands from all sources are applied between ticks.
+    pub fn listen(&mut self, commands: Receiver<TuneCommand>) {
+        self.commands.push(commands);
+    }
+
+    /// Set the presets, which are replaced when the preset file is reloaded.
+    pub fn set_presets(&mut self, presets: PresetFile) {
+        self.presets = presets;
+    }
+
+    /// Get the current presets.
+    pub fn get_presets(&self) -> &PresetFile {
+        &self.presets
+    }
+
+    /// Add an effect driving the given lights.
+    ///
+    /// The lights are prepared by the effect and ticked right away.
+    /// Unknown lights are ignored.
+    pub fn add_effect(&mut self, setup: EffectSetup) -> EffectId {
+        self.add_effect_fading(setup, None)
+    }
+
+    /// Add an effect, fading the lights over to its first frame.
+    ///
+    /// The fade is a transition time in multiples of 100ms.
+    pub fn add_effect_fading(&mut self, setup: EffectSetup, fade_in: Option<u16>) -> EffectId {
+        let id = EffectId(self.next_effect);
+        self.next_effect += 1;
+        let mut effect = (setup.info.create)(&setup.params);
+        let now = Instant::now();
+        for uid in &setup.lights {
+            let (light, bridge) = match (self.home.light(uid), self.home.light_client(uid)) {
+                (Some(light), Some(client)) => (light, client.get_bridge_id()),
+                _ => continue,
+            };
+            let light_info = EffectLight { uid: uid.clone(), id: light.get_id() };
+
+            // Prepare the light, based on the state left by previous effects
+            let state = self.states.get(uid).unwrap_or_else(|| light.get_state());
+            if let Some(batch) = effect.init(&light_info, state) {
+                self.send_now(uid, &bridge, &batch);
+            }
+
+            self.queue.push(Reverse((now, self.next_entry)));
+            self.entries.insert(self.next_entry, Entry { light: light_info, bridge, effect: id, fade_in });
+            self.next_entry += 1;
+        }
+        self.effects.insert(id, Slot { setup, effect, transition: None });
+        id
+    }
+
+    /// Remove an effect, leaving its lights as the effect decides.
+    pub fn remove_effect(&mut self, id: EffectId) {
+        let mut slot = match self.effects.remove(&id) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let batches: Vec<(String, String, HueLightBatch)> = self
+            .entries
+            .values()
+            .filter(|entry| entry.effect == id)
+            .filter_map(|entry| {
+                let batch = slot.effect.teardown(&entry.light)?;
+                Some((entry.light.uid.clone(), entry.bridge.clone(), batch))
+            })
+            .collect();
+        for (uid, bridge, batch) in batches {
+            self.send_now(&uid, &bridge, &batch);
+        }
+
+        // Queued ticks of the dropped lights are skipped
+        self.entries.retain(|_, entry| entry.effect != id);
+    }
+
+    /// Remove all effects.
+    pub fn remove_all(&mut self) {
+        let ids: Vec<EffectId> = self.effects.keys().cloned().collect();
+        for id in ids {
+            self.remove_effect(id);
+        }
+    }
+
+    /// Send a batch outside of the frames, like the preparation of a light.
+    ///
+    /// Waits until the bridge accepts commands, since these batches
+    /// count against the same rate budget as the frames.
+    fn send_now(&mut self, uid: &str, bridge: &str, batch: &HueLightBatch) {
+        if let Some(wait) = self.budget.wait_time(bridge, Instant::now()) {
+            std::thread::sleep(wait);
+        }
+        let result = self.states.send(self.home, uid, batch);
+        if !matches!(result, Ok(false)) {
+            self.budget.consume(bridge, Instant::now());
+        }
+        if let Err(err) = result {
+            println!("[Light {}] {}", uid, err);
+        }
+    }
+
+    /// Test whether all lights of the running effects are switched off.
+    ///
+    /// Fetches the current state from the bridges, since lights
+    /// may be switched off by a switch or another app.
+    fn lights_off(&self) -> bool {
+        let active: HashSet<&str> = self.entries.values().map(|entry| entry.light.uid.as_str()).collect();
+        if active.is_empty() {
+            return false;
+        }
+        for client in self.home.clients() {
+            let lights = match client.fetch_lights() {
+                Ok(lights) => lights,
+                Err(err) => {
+                    println!("[Bridge {}] {}", client.get_bridge_id(), err);
+                    return false;
+                }
+            };
+            let on = lights
+                .iter()
+                .filter(|light| active.contains(light.get_unique_id().as_str()))
+                .any(|light| light.get_state().is_on());
+            if on {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Change a parameter of all running effects that have it.
+    ///
+    /// With a scope, only effects with that name or target are changed.
+    fn tune(&mut self, scope: Option<&str>, name: &str, adjustment: Adjustment) {
+        let mut in_scope = false;
+        let mut found = false;
+        for slot in self.effects.values_mut() {
+            let setup = &mut slot.setup;
+            let matches = |scope: &str| setup.info.name == scope || setup.target.as_deref() == Some(scope);
+            if !scope.is_none_or(matches) {
+                continue;
+            }
+            in_scope = true;
+            let spec = match setup.params.spec(name) {
+                Some(spec) => spec,
+                None => continue,
+            };
+            found = true;
+
+            // Keep the value as an override, so it survives reloading presets
+            let val = adjustment.apply(setup.params.get(spec.name));
+            match setup.params.set_value(spec.name, val) {
+                Ok(()) => {
+                    setup.overrides.set_value(spec.name, val).ok();
+                    if let Some(transition) = &mut slot.transition {
+                        transition.to.set_value(spec.name, val).ok();
+                    }
+                    slot.effect.update(&setup.params);
+                    println!("[Effect {}] {} = {}", setup.info.name, spec.name, val);
+                    for warning in setup.params.warnings() {
+                        println!("[Effect {}] Warning: {}", setup.info.name, warning);
+                    }
+                }
+                Err(err) => println!("[Effect {}] {}", setup.info.name, err),
+            }
+        }
+        if let (Some(scope), false) = (scope, in_scope) {
+            println!("No running effect is called or assigned to '{}'.", scope);
+        } else if !found {
+            println!("Unknown parameter '{}'.", name);
+        }
+    }
+
+    /// Start blending the effects over to reloaded presets.
+    ///
+    /// Effects whose preset became invalid keep their parameters.
+    fn reload(&mut self, presets: PresetFile) {
+        let now = Instant::now();
+        for slot in self.effects.values_mut() {
+            let setup = &slot.setup;
+            let preset = match &setup.preset {
+                Some(preset) => preset,
+                None => continue,
+            };
+            let params = match setup.resolve(&presets) {
+                Ok(params) => params,
+                Err(err) => {
+                    println!("[Effect {}] Keeping the current parameters: {}", setup.info.name, err);
+                    continue;
+                }
+            };
+            println!("[Effect {}] Blending over to the reloaded preset '{}'", setup.info.name, preset);
+            for warning in params.warnings() {
+                println!("[Effect {}] Warning: {}", setup.info.name, warning);
+            }
+            slot.transition = Some(Transition { from: setup.params.clone(), to: params, start: now });
+        }
+        self.presets = presets;
+    }
+
+    /// Advance the effects that blend over to new parameters.
+    fn advance_transitions(&mut self, now: Instant) {
+        for slot in self.effects.values_mut() {
+            let transition = match &slot.transition {
+                Some(transition) => transition,
+                None => continue,
+            };
+            let elapsed = now.duration_since(transition.start);
+            let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+            let t = (elapsed / RELOAD_FADE.as_secs() as f64).min(1.0);
+            slot.setup.params = Params::interpolate(&transition.from, &transition.to, t);
+            slot.effect.update(&slot.setup.params);
+            if t >= 1.0 {
+                slot.transition = None;
+            }
+        }
+    }
+
+    /// Apply pending tuning commands.
+    fn handle_commands(&mut self) {
+        let commands: Vec<TuneCommand> = self
+            .commands
+            .iter()
+            .flat_map(|commands| commands.try_iter())
+            .collect();
+        for command in commands {
+            match command {
+                TuneCommand::Set { scope, name, adjustment } => self.tune(scope.as_deref(), &name, adjustment),
+                TuneCommand::Show => {
+                    for slot in self.effects.values() {
+                        print!("[Effect {}] Parameters:\n{}", slot.setup.info.name, slot.setup.params);
+                    }
+                }
+                TuneCommand::Presets(presets) => self.reload(presets),
+            }
+        }
+    }
+
+    /// Drive the effects until they are stopped.
+    ///
+    /// Also stops once the deadline passed or, if requested, all lights
+    /// were switched off. The effects are not removed, so they can be
+    /// resumed or torn down.
+    pub fn run(&mut self, control: &EffectControl) -> StopReason {
+        self.run_until(control, None)
+    }
+
+    /// Drive the effects until they are stopped or a time is reached.
+    ///
+    /// Returns `StopReason::Until` if the time was reached,
+    /// otherwise the reason the effects stopped.
+    pub fn run_until(&mut self, control: &EffectControl, until: Option<Instant>) -> StopReason {
+        while control.is_running() {
+
+            // Return once the time is reached
+            let now = Instant::now();
+            if until.is_some_and(|until| now >= until) {
+                return StopReason::Until;
+            }
+
+            // Stop once the deadline passed
+            if self.deadline.is_some_and(|deadline| now >= deadline) {
+                println!("Time is up, stopping...");
+                return StopReason::Deadline;
+            }
+
+            // Stop once all lights were switched off
+            if let Some(check) = self.lights_off_check {
+                if now >= check {
+                    if self.lights_off() {
+                        println!("All lights are off, stopping...");
+                        return StopReason::LightsOff;
+                    }
+                    self.lights_off_check = Some(Instant::now() + LIGHTS_OFF_INTERVAL);
+                }
+            }
+
+            // Apply tuning commands, even while paused
+            self.handle_commands();
+            self.advance_transitions(Instant::now());
+
+            // Wait while the effects are paused
+            if control.is_paused() {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            // Wait for the next deadline
+            let Reverse((deadline, index)) = match self.queue.peek() {
+                Some(next) => *next,
+                None => return StopReason::Finished,
+            };
+            let now = Instant::now();
+            if deadline > now {
+                std::thread::sleep((deadline - now).min(POLL_INTERVAL));
+                continue;
+            }
+            self.queue.pop();
+
+            // Drop lights of removed effects
+            let entry = match self.entries.get_mut(&index) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let effect = match self.effects.get_mut(&entry.effect) {
+                Some(slot) => &mut slot.effect,
+                None => continue,
+            };
+
+            // Delay the light until its bridge accepts commands
+            if let Some(wait) = self.budget.wait_time(&entry.bridge, now) {
+                self.queue.push(Reverse((now + wait, index)));
+                continue;
+            }
+
+            // Compute the next frame, fading over from the previous effect
+            let mut frame = effect.tick(&entry.light);
+            if let Some(fade_in) = entry.fade_in.take() {
+                if let Some(batch) = &mut frame.batch {
+                    batch.transition_time(fade_in);
+                }
+                frame.delay = frame.delay.max(Duration::from_millis(u64::from(fade_in) * 100));
+            }
+
+            // Send the frame
+            if let Some(batch) = frame.batch {
+                match self.states.send(self.home, &entry.light.uid, &batch) {
+                    Ok(true) => self.budget.consume(&entry.bridge, now),
+                    Ok(false) => (),
+                    Err(err) => {
+                        self.budget.consume(&entry.bridge, now);
+                        println!("[Light {}] {}", entry.light.uid, err);
+                    }
+                }
+            }
+            self.queue.push(Reverse((now + frame.delay, index)));
+        }
+        StopReason::Stopped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::find_effect;
+
+    #[test]
+    fn budget_allows_bursts_up_to_the_rate() {
+        let mut budget = RateBudget::new(2.0);
+        let now = Instant::now();
+        for _ in 0..2 {
+            assert_eq!(budget.wait_time("a", now), None);
+            budget.consume("a", now);
+        }
+        assert_eq!(budget.wait_time("a", now), Some(Duration::from_millis(500)));
+
+        // Every bridge has its own budget
+        assert_eq!(budget.wait_time("b", now), None);
+    }
+
+    #[test]
+    fn budget_refills_over_time() {
+        let mut budget = RateBudget::new(2.0);
+        let now = Instant::now();
+        budget.consume("a", now);
+        budget.consume("a", now);
+        assert_eq!(budget.wait_time("a", now + Duration::from_millis(250)), Some(Duration::from_millis(250)));
+        assert_eq!(budget.wait_time("a", now + Duration::from_millis(500)), None);
+
+        // Idle time does not save up more than a second of commands
+        let later = now + Duration::from_secs(10);
+        budget.consume("a", later);
+        budget.consume("a", later);
+        assert!(budget.wait_time("a", later).is_some());
+    }
+
+    #[test]
+    fn skips_lights_of_removed_effects() {
+        let home = HueHome::new(Vec::new());
+        let mut scheduler = Scheduler::new(&home, &RunOptions::default());
+        let now = Instant::now();
+
+        // A light whose entry was dropped, and one whose effect is gone
+        scheduler.entries.insert(1, Entry {
+            light: EffectLight { uid: "light".to_string(), id: 1 },
+            bridge: "bridge".to_string(),
+            effect: EffectId(5),
+            fade_in: None,
+        });
+        scheduler.queue.push(Reverse((now, 0)));
+        scheduler.queue.push(Reverse((now, 1)));
+
+        // Both are dropped from the queue, which leaves nothing to run
+        let control = EffectControl::new();
+        assert_eq!(scheduler.run_until(&control, Some(now + Duration::from_secs(1))), StopReason::Finished);
+        assert!(scheduler.queue.is_empty());
+    }
+
+    #[test]
+    fn tunes_effects_in_scope() {
+        let home = HueHome::new(Vec::new());
+        let mut scheduler = Scheduler::new(&home, &RunOptions::default());
+        let fireplace = find_effect("fireplace").unwrap();
+        let mut ids = Vec::new();
+        for target in &["living", "bar"] {
+            let mut setup = EffectSetup::new(fireplace, None, Params::new(fireplace.params), Vec::new(), &PresetFile::default()).unwrap();
+            setup.target = Some(target.to_string());
+            ids.push(scheduler.add_effect(setup));
+        }
+        let brightness = |scheduler: &Scheduler, id| scheduler.effects[id].setup.params.get("brightness_base");
+
+        // Only the effect assigned to the target changes
+        scheduler.tune(Some("living"), "bb", Adjustment::To(50.0));
+        assert_eq!(brightness(&scheduler, &ids[0]), 50.0);
+        assert_eq!(brightness(&scheduler, &ids[1]), 70.0);
+
+        // Without a scope, or scoped by effect name, all of them change
+        scheduler.tune(None, "bb", Adjustment::By(10.0));
+        assert_eq!(brightness(&scheduler, &ids[0]), 60.0);
+        assert_eq!(brightness(&scheduler, &ids[1]), 80.0);
+        scheduler.tune(Some("fireplace"), "bb", Adjustment::To(90.0));
+        assert_eq!(brightness(&scheduler, &ids[0]), 90.0);
+        assert_eq!(brightness(&scheduler, &ids[1]), 90.0);
+
+        // The value is kept as an override
+        assert_eq!(scheduler.effects[&ids[1]].setup.overrides.get("brightness_base"), 90.0);
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use super::{EffectControl, PresetFile};
+
+/// Change of a parameter value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adjustment {
+
+    /// Set the parameter to a value
+    To(f64),
+
+    /// Nudge the parameter by an amount
+    By(f64),
+}
+
+impl Adjustment {
+
+    /// Parse `50`, `+10` or `-5`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let val: f64 = text.parse().ok()?;
+        if text.starts_with('+') || text.starts_with('-') {
+            Some(Adjustment::By(val))
+        } else {
+            Some(Adjustment::To(val))
+        }
+    }
+
+    /// Get the adjusted value.
+    pub fn apply(self, current: f64) -> f64 {
+        match self {
+            Adjustment::To(val) => val,
+            Adjustment::By(val) => current + val,
+        }
+    }
+}
+
+/// Command for tuning running effects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TuneCommand {
+
+    /// Change a parameter of all effects that have it,
+    /// or only of the effects matching the scope
+    Set { scope: Option<String>, name: String, adjustment: Adjustment },
+
+    /// Print the current parameters
+    Show,
+
+    /// Blend over to the presets of a reloaded preset file
+    Presets(PresetFile),
+}
+
+/// Help text for the tuning commands.
+const TUNING_HELP: &str = "\
+Commands:
+  set <param> <value>   Set a parameter, e.g. set brightness_base 50
+  set <param> +<n>      Nudge a parameter, e.g. set sp -0.005
+  set <scope>.<param> <value>
+                        Only change effects with that name or target,
+                        e.g. set living.brightness_base 50
+  show                  Print the current parameters
+  pause, resume         Pause or resume the effects
+  stop                  Stop the effects";
+
+/// Parse a line typed on the terminal.
+///
+/// Pausing and stopping is applied to the control right away,
+/// other commands are returned.
+fn parse_line(line: &str, control: &EffectControl) -> Option<TuneCommand> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [] => None,
+        ["set", name, value] => match Adjustment::parse(value) {
+            Some(adjustment) => {
+                let (scope, name) = match name.split_once('.') {
+                    Some((scope, name)) => (Some(scope.to_string()), name),
+                    None => (None, *name),
+                };
+                Some(TuneCommand::Set { scope, name: name.to_string(), adjustment })
+            }
+            None => {
+                println!("Invalid value '{}'.", value);
+                None
+            }
+        },
+        ["show"] => Some(TuneCommand::Show),
+        ["pause"] => {
+            control.set_paused(true);
+            println!("Paused.");
+            None
+        }
+        ["resume"] => {
+            control.set_paused(false);
+            println!("Resumed.");
+            None
+        }
+        ["stop"] => {
+            control.stop();
+            None
+        }
+        _ => {
+            println!("{}", TUNING_HELP);
+            None
+        }
+    }
+}
+
+/// Read tuning commands from the terminal.
+///
+/// The returned receiver is handed to the `Scheduler`, which applies
+/// the commands between ticks. Reading ends with the input.
+pub fn spawn_stdin_tuner(control: EffectControl) -> Receiver<TuneCommand> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if let Some(command) = parse_line(&line, &control) {
+                if sender.send(command).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_nudge_the_value() {
+        assert_eq!(Adjustment::parse("50"), Some(Adjustment::To(50.0)));
+        assert_eq!(Adjustment::parse("+10"), Some(Adjustment::By(10.0)));
+        assert_eq!(Adjustment::parse("-0.005"), Some(Adjustment::By(-0.005)));
+        assert_eq!(Adjustment::parse("lots"), None);
+
+        assert_eq!(Adjustment::To(50.0).apply(70.0), 50.0);
+        assert_eq!(Adjustment::By(-5.0).apply(70.0), 65.0);
+    }
+
+    #[test]
+    fn parses_set_commands() {
+        let control = EffectControl::new();
+        assert_eq!(
+            parse_line("set bb +10", &control),
+            Some(TuneCommand::Set { scope: None, name: "bb".to_string(), adjustment: Adjustment::By(10.0) })
+        );
+        assert_eq!(
+            parse_line("  set living.brightness_base 50 ", &control),
+            Some(TuneCommand::Set {
+                scope: Some("living".to_string()),
+                name: "brightness_base".to_string(),
+                adjustment: Adjustment::To(50.0),
+            })
+        );
+        assert_eq!(parse_line("set bb bright", &control), None);
+        assert_eq!(parse_line("set bb", &control), None);
+    }
+
+    #[test]
+    fn applies_control_commands_right_away() {
+        let control = EffectControl::new();
+        assert_eq!(parse_line("pause", &control), None);
+        assert!(control.is_paused());
+        assert_eq!(parse_line("resume", &control), None);
+        assert!(!control.is_paused());
+        assert_eq!(parse_line("show", &control), Some(TuneCommand::Show));
+        assert!(control.is_running());
+        assert_eq!(parse_line("stop", &control), None);
+        assert!(!control.is_running());
+    }
+}
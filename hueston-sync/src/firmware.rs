@@ -0,0 +1,90 @@
+use hueston::HueBridgeClient;
+use super::errors::*;
+
+/// Report the firmware state of all bridges and their outdated devices.
+///
+/// Optionally make the bridges check for updates, or install them.
+/// Errors of a bridge are printed, the other bridges are still reported.
+pub fn report_firmware(clients: &[HueBridgeClient], check: bool, install: bool) -> Result<()> {
+    for client in clients {
+        let bridge_id = client.get_bridge_id();
+
+        // Trigger an update check or installation
+        if check {
+            if let Err(err) = client.check_for_updates() {
+                println!("[Bridge {}] Unable to check for updates: {}", bridge_id, err);
+                continue;
+            }
+        }
+        if install {
+            if let Err(err) = client.install_updates() {
+                println!("[Bridge {}] Unable to install updates: {}", bridge_id, err);
+                continue;
+            }
+        }
+
+        // Report the bridge state
+        let update = match client.fetch_sw_update() {
+            Ok(update) => update,
+            Err(err) => {
+                println!("[Bridge {}] {}", bridge_id, err);
+                continue;
+            }
+        };
+        let auto_install = update.get_auto_install();
+        println!(
+            "[Bridge {}] {}: {:?}, auto-install {}{}",
+            bridge_id,
+            client.get_name(),
+            update.get_state(),
+            if auto_install.is_on() { "on" } else { "off" },
+            auto_install.get_update_time().map(|time| format!(" at {}", time)).unwrap_or_default(),
+        );
+        if update.get_bridge().get_state().is_outdated() {
+            println!("  Bridge: {:?}", update.get_bridge().get_state());
+        }
+
+        // Report outdated lights
+        let lights = client.fetch_lights().unwrap_or_else(|err| {
+            println!("[Bridge {}] Unable to fetch lights: {}", bridge_id, err);
+            Vec::new()
+        });
+        for light in lights {
+            let state = match light.get_sw_update() {
+                Some(update) => update.get_state(),
+                None => continue,
+            };
+            if state.is_outdated() {
+                println!(
+                    "  {} ({}, {}): {:?}",
+                    light.get_name(),
+                    light.get_model_id(),
+                    light.get_sw_version(),
+                    state,
+                );
+            }
+        }
+
+        // Report outdated sensors
+        let sensors = client.fetch_sensors().unwrap_or_else(|err| {
+            println!("[Bridge {}] Unable to fetch sensors: {}", bridge_id, err);
+            Vec::new()
+        });
+        for sensor in sensors {
+            let state = match sensor.get_sw_update() {
+                Some(update) => update.get_state(),
+                None => continue,
+            };
+            if state.is_outdated() {
+                println!(
+                    "  {} ({}, {}): {:?}",
+                    sensor.get_name(),
+                    sensor.get_model_id(),
+                    sensor.get_sw_version().map(String::as_str).unwrap_or("unknown"),
+                    state,
+                );
+            }
+        }
+    }
+    Ok(())
+}
\ No newline at end of file
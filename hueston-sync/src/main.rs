@@ -1,20 +1,56 @@
 // Import crates
 #[macro_use]
 extern crate error_chain;
-use clap::clap_app;
-use hueston::HueLightBatch;
+use clap::{clap_app, App, Arg, SubCommand};
+use hueston::{
+    DiscoveryOptions,
+    HueBridgeClient,
+    HueHome,
+    HueLightBatch,
+    LightSnapshot,
+    Relocation,
+    RetryPolicy,
+};
 use human_panic::setup_panic;
 
 // Import std stuff
 use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
 
 // Import modules
+mod backup;
 mod discovery;
 mod effects;
 mod errors;
+mod firmware;
+mod selection;
 mod state;
+use self::backup::{backup_bridges, restore_bridge};
 use self::discovery::{discover_bridges, DiscoveryMode};
-use self::effects::{ColorsweepEffect, FireplaceSimulation};
+use self::firmware::report_firmware;
+use self::selection::{selection_args, LightSelection};
+use self::effects::{
+    find_effect,
+    leave_lights,
+    parse_duration,
+    run_args,
+    run_effects,
+    run_playlist,
+    simulate_command,
+    spawn_preset_watcher,
+    spawn_stdin_tuner,
+    Assignment,
+    EffectControl,
+    EffectSetup,
+    Params,
+    Playlist,
+    PresetFile,
+    RunOptions,
+    TuneCommand,
+    DEFAULT_PRESET_FILE,
+};
 use self::errors::*;
 use self::state::StartupState;
 
@@ -29,6 +65,98 @@ fn read_startup_state() -> StartupState {
     try_read_startup_state().unwrap_or_else(StartupState::default)
 }
 
+/// Build the `light` subcommand.
+fn light_command() -> App<'static, 'static> {
+    let arg = |name: &'static str, help: &'static str| Arg::with_name(name).takes_value(true).help(help);
+    SubCommand::with_name("light")
+        .about("Set the state of lights")
+        .args(&selection_args())
+        .arg(arg("hue", "Set hue").short("h").long("hue"))
+        .arg(arg("sat", "Set saturation").short("s").long("sat"))
+        .arg(arg("bri", "Set brightness").short("b").long("bri"))
+        .arg(arg("tt", "Set transition time").long("tt"))
+}
+
+/// Build the `run` subcommand.
+fn run_command() -> App<'static, 'static> {
+    SubCommand::with_name("run")
+        .about("Run different effects on different lights at once")
+        .args(&run_args())
+        .arg(
+            Arg::with_name("assign")
+                .long("assign")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("Assign an effect to groups or lights, e.g. living=fireplace"),
+        )
+}
+
+/// Build the `playlist` subcommand.
+fn playlist_command() -> App<'static, 'static> {
+    SubCommand::with_name("playlist")
+        .about("Cycle through effects and presets")
+        .args(&selection_args())
+        .args(&run_args())
+        .arg(Arg::with_name("file").required(true).help("The playlist to play"))
+        .arg(Arg::with_name("shuffle").long("shuffle").help("Shuffle the entries for every round"))
+        .arg(Arg::with_name("once").long("once").help("Stop after the last entry instead of starting over"))
+        .arg(
+            Arg::with_name("crossfade")
+                .long("crossfade")
+                .takes_value(true)
+                .help("Time for fading from one entry to the next, e.g. 10s"),
+        )
+}
+
+/// Print all effect presets with their parameters.
+fn list_presets(presets: &PresetFile) {
+    let mut empty = true;
+    for (effect, name, preset) in presets.iter() {
+        let values: Vec<String> = preset
+            .get_values()
+            .iter()
+            .map(|(param, val)| format!("{}={}", param, val))
+            .collect();
+        println!("{} {}: {}", effect, name, values.join(", "));
+        empty = false;
+    }
+    if empty {
+        println!("No presets found.");
+    }
+}
+
+/// Warn about questionable parameters of an effect, and print them if requested.
+///
+/// Returns whether the parameters were printed.
+fn report_params(effect: &str, params: &Params, show: bool) -> bool {
+    for warning in params.warnings() {
+        println!("[Effect {}] Warning: {}", effect, warning);
+    }
+    if show {
+        print!("[Effect {}] Parameters:\n{}", effect, params);
+    }
+    show
+}
+
+/// Read tuning commands from the terminal and reload the preset file on changes.
+fn tuning_sources(control: &EffectControl, preset_path: &str) -> Vec<Receiver<TuneCommand>> {
+    vec![
+        spawn_stdin_tuner(control.clone()),
+        spawn_preset_watcher(PathBuf::from(preset_path)),
+    ]
+}
+
+/// Stop running effects on Ctrl+C and termination.
+fn install_stop_handler() -> Result<EffectControl> {
+    let control = EffectControl::new();
+    let handler = control.clone();
+    ctrlc::set_handler(move || handler.stop())
+        .chain_err(|| "Unable to install signal handler.")?;
+    Ok(control)
+}
+
 /// Main entry point.
 fn main() -> self::errors::Result<()> {
     // Setup human-friendly error handing
@@ -40,118 +168,278 @@ fn main() -> self::errors::Result<()> {
         (author: "Marco Quinten <splittydev@gmail.com>")
         (about: "Philips Hue remote control")
 
-        // Light control
-        (@subcommand light =>
-            (@arg light: +takes_value -l --light "Choose the light")
-            (@arg hue: +takes_value -h --hue "Set hue")
-            (@arg sat: +takes_value -s --sat "Set saturation")
-            (@arg bri: +takes_value -b --bri "Set brightness")
-            (@arg tt: +takes_value --tt "Set transition time")
+        // Connection settings
+        (@arg timeout: +takes_value --timeout "Request timeout in milliseconds")
+        (@arg attempts: +takes_value --attempts "Maximum number of attempts per request")
+        (@arg record: +takes_value --record "Record bridge traffic to cassettes in this directory")
+        (@arg preset_file: +takes_value --("preset-file") "Read effect presets from this file")
+
+        // Effect presets
+        (@subcommand presets =>
+            (about: "List the effect presets")
         )
 
-        // Simulations
-        (@subcommand simulate =>
-
-            // Fireplace simulation
-            (@subcommand fireplace =>
-                (@arg bri_bas: +takes_value --bb "Brightness base")
-                (@arg bri_var: +takes_value --bv "Brightness variance")
-                (@arg sat_bas: +takes_value --sb "Saturation base")
-                (@arg sat_var: +takes_value --sv "Saturation variance")
-                (@arg tra_bas: +takes_value --tb "Transition time base")
-                (@arg tra_var: +takes_value --tv "Transition time variance")
-                (@arg spa_bri_bas: +takes_value --sbb "Spark brightness base")
-                (@arg spa_bri_var: +takes_value --sbv "Spark brightness variance")
-                (@arg spa_sat_bas: +takes_value --ssb "Spark saturation base")
-                (@arg spa_sat_var: +takes_value --ssv "Spark saturation variance")
-                (@arg spa_pro: +takes_value --sp "Spark probability")
-            )
-
-            // Color sweep effect
-            (@subcommand colorsweep =>
-            )
+        // Backup and restore
+        (@subcommand backup =>
+            (@arg dir: +takes_value -d --dir "Directory to write the archives to")
+        )
+        (@subcommand restore =>
+            (@arg archive: +required "The archive to restore")
+            (@arg bridge: +takes_value -b --bridge "ID of the bridge to restore to")
+        )
+
+        // Firmware updates
+        (@subcommand firmware =>
+            (@arg check: --check "Make the bridges check for updates")
+            (@arg install: --install "Install updates that are ready")
         )
     )
+
+    // Light control
+    .subcommand(light_command())
+
+    // Simulations, generated from the effect registry
+    .subcommand(simulate_command())
+    .subcommand(run_command())
+    .subcommand(playlist_command())
     .get_matches();
 
+    // Load the effect presets, the default file is optional
+    let preset_path = matches.value_of("preset_file").unwrap_or(DEFAULT_PRESET_FILE);
+    let presets = match matches.value_of("preset_file") {
+        Some(path) => PresetFile::load(path)?,
+        None => PresetFile::load_if_exists(preset_path)?,
+    };
+
+    // Test whether the presets command was specified
+    if matches.subcommand_matches("presets").is_some() {
+        list_presets(&presets);
+        return Ok(());
+    }
+
     // Load startup state from disk
     let mut state = read_startup_state();
 
     // Discover bridges if necessary
     discover_bridges(&mut state, DiscoveryMode::Initial);
 
+    // Configure the bridge clients
+    let mut builder = HueBridgeClient::builder().connect_timeout(Duration::from_secs(2));
+    if let Some(val) = matches.value_of("timeout") {
+        let millis = val.parse().chain_err(|| "Invalid timeout.")?;
+        builder = builder.timeout(Duration::from_millis(millis));
+    }
+    if let Some(val) = matches.value_of("attempts") {
+        let attempts = val.parse().chain_err(|| "Invalid number of attempts.")?;
+        builder = builder.retry_policy(RetryPolicy::default().with_max_attempts(attempts));
+    }
+
     // Create bridge clients from saved state
+    let record_dir = matches.value_of("record").map(Path::new);
+    if let Some(dir) = record_dir {
+        std::fs::create_dir_all(dir).chain_err(|| "Unable to create the cassette directory.")?;
+    }
     let clients = state
-        .connect_bridge_clients()
+        .connect_bridge_clients(&builder, record_dir)
         .chain_err(|| "Unable to connect bridge clients.")?;
 
-    // Iterate over bridges
-    for (i, client) in clients.iter().enumerate() {
+    // Persist newly cached bridge information
+    state.save_to_disk()?;
+
+    // Make sure the bridges are reachable
+    let mut live_clients = Vec::new();
+    for (i, mut client) in clients.into_iter().enumerate() {
         println!("[Bridge {}] {}", i + 1, client.get_name());
 
-        // Test whether the simulate command was specified
-        if let Some(matches) = matches.subcommand_matches("simulate") {
-            // Fetch lights
-            let lights = client.fetch_lights().unwrap();
-
-            // Test whether the fireplace simulation was requested
-            if let Some(matches) = matches.subcommand_matches("fireplace") {
-                /// A macro for quick argument extraction
-                macro_rules! extract {
-                    ($name:ident || $default:expr) => {
-                        matches
-                            .value_of(stringify!($name))
-                            .map(|v| v.parse().unwrap_or($default))
-                            .unwrap_or($default)
-                    };
-                }
-
-                // Construct the fireplace simulation
-                let sim = FireplaceSimulation {
-                    brightness_base: extract!(bri_bas || 70),
-                    brightness_variance: extract!(bri_var || 10),
-                    brightness_spark_base: extract!(bri_spa_bas || 110),
-                    brightness_spark_variance: extract!(bri_spa_var || 10),
-                    saturation_base: extract!(sat_bas || 247),
-                    saturation_variance: extract!(sat_var || 7),
-                    saturation_spark_base: extract!(sat_spa_bas || 227),
-                    saturation_spark_variance: extract!(sat_spa_var || 27),
-                    transition_time_base: extract!(tra_bas || 3),
-                    transition_time_variance: extract!(tra_var || 2),
-                    spark_probability: extract!(spa_pro || 0.01),
-                };
+        // Follow the bridge if its address changed
+        match client.ensure_alive(&DiscoveryOptions::default()) {
+            Ok(Relocation::Unchanged) => (),
+            Ok(Relocation::Moved { old_url, new_url }) => {
+                println!("[Bridge {}] Moved from {} to {}", i + 1, old_url, new_url);
+                state.update_bridge((&client).into());
+                state.save_to_disk()?;
+            }
+            Err(err) => {
+                println!("[Bridge {}] Not responding: {}", i + 1, err);
+                continue;
+            }
+        }
 
-                // Run the simulation
-                sim.run(&client, lights);
+        // Detect the kind of gateway and remember it
+        let profile = client.get_profile();
+        match client.detect_profile() {
+            Ok(detected) if detected != profile => {
+                println!("[Bridge {}] Detected a {} gateway", i + 1, detected);
+                state.update_bridge((&client).into());
+                state.save_to_disk()?;
             }
-            // Test whether the color-sweep effect was requested
-            else if let Some(_matches) = matches.subcommand_matches("colorsweep") {
-                // Run the effect
-                let effect = ColorsweepEffect {};
-                effect.run(&client, lights);
+            Ok(_) => (),
+            Err(err) => println!("[Bridge {}] Unable to detect the gateway type: {}", i + 1, err),
+        }
+
+        // Detect the supported features
+        match client.detect_features() {
+            Ok(features) => println!("[Bridge {}] API version {}", i + 1, features.get_api_version()),
+            Err(err) => println!("[Bridge {}] Unable to detect features: {}", i + 1, err),
+        }
+        live_clients.push(client);
+    }
+
+    // Combine all bridges into one home
+    let mut home = HueHome::new(live_clients);
+
+    // Test whether the backup command was specified
+    if let Some(matches) = matches.subcommand_matches("backup") {
+        let dir = matches.value_of("dir").unwrap_or(".");
+        return backup_bridges(home.clients(), dir);
+    }
+
+    // Test whether the firmware command was specified
+    if let Some(matches) = matches.subcommand_matches("firmware") {
+        let check = matches.is_present("check");
+        let install = matches.is_present("install");
+        return report_firmware(home.clients(), check, install);
+    }
+
+    // Test whether the restore command was specified
+    if let Some(matches) = matches.subcommand_matches("restore") {
+        let path = matches.value_of("archive").unwrap();
+        return restore_bridge(home.clients(), path, matches.value_of("bridge"));
+    }
+
+    // Fetch lights from all bridges, skipping the ones that fail
+    let errors = home.refresh();
+    for (bridge_id, err) in &errors {
+        println!("[Bridge {}] Unable to fetch lights: {}", bridge_id, err);
+    }
+    if !errors.is_empty() && errors.len() == home.clients().len() {
+        bail!("Unable to fetch lights from bridges.");
+    }
+
+    // Test whether the simulate command was specified
+    if let Some(matches) = matches.subcommand_matches("simulate") {
+        let control = install_stop_handler()?;
+
+        // Choose the lights and remember their states before the effect starts
+        let lights = LightSelection::from_matches(matches)?.select(&home)?;
+        let snapshot = LightSnapshot::capture(&home, &lights);
+        let options = RunOptions::from_matches(matches)?;
+
+        // Construct the requested effect
+        let (name, effect_matches) = matches.subcommand();
+        let info = find_effect(name).chain_err(|| format!("Unknown effect '{}'.", name))?;
+        let mut overrides = Params::new(info.params);
+        let mut preset = None;
+        if let Some(effect_matches) = effect_matches {
+            preset = effect_matches.value_of("preset");
+            overrides.apply_matches(effect_matches)?;
+        }
+        let setup = EffectSetup::new(info, preset, overrides, lights.clone(), &presets)?;
+        if report_params(name, &setup.params, options.show_params) {
+            return Ok(());
+        }
+
+        // Run the effect, tuned from the terminal and the preset file
+        println!("Type 'help' for commands to tune the running effect.");
+        let commands = tuning_sources(&control, preset_path);
+        let reason = run_effects(vec![setup], &home, &control, &options, commands);
+        leave_lights(&home, &snapshot, &lights, &options, reason)?;
+    }
+    // Test whether the run command was specified
+    else if let Some(matches) = matches.subcommand_matches("run") {
+        let control = install_stop_handler()?;
+        let options = RunOptions::from_matches(matches)?;
+
+        // Construct the assigned effects, every light may only be driven once
+        let mut effects = Vec::new();
+        let mut all_lights: Vec<String> = Vec::new();
+        for text in matches.values_of("assign").unwrap() {
+            let assignment = Assignment::parse(text)?;
+            let lights = LightSelection::from_target(&home, &assignment.target)?
+                .select(&home)
+                .chain_err(|| format!("Unable to select lights for '{}'.", assignment.target))?;
+            if let Some(uid) = lights.iter().find(|uid| all_lights.contains(uid)) {
+                bail!("Light {} is assigned more than once.", uid);
             }
+            println!("Running {} on {} lights of '{}'", assignment.effect.name, lights.len(), assignment.target);
+            all_lights.extend(lights.iter().cloned());
+            let mut setup = EffectSetup::new(assignment.effect, assignment.preset(), assignment.overrides()?, lights, &presets)?;
+            setup.target = Some(assignment.target.clone());
+            report_params(assignment.effect.name, &setup.params, options.show_params);
+            effects.push(setup);
+        }
+
+        if options.show_params {
+            return Ok(());
+        }
+
+        // Run the effects, tuned from the terminal and the preset file
+        let snapshot = LightSnapshot::capture(&home, &all_lights);
+        println!("Type 'help' for commands to tune the running effects.");
+        let commands = tuning_sources(&control, preset_path);
+        let reason = run_effects(effects, &home, &control, &options, commands);
+        leave_lights(&home, &snapshot, &all_lights, &options, reason)?;
+    }
+    // Test whether the playlist command was specified
+    else if let Some(matches) = matches.subcommand_matches("playlist") {
+        let control = install_stop_handler()?;
+        let options = RunOptions::from_matches(matches)?;
+
+        // Load the playlist, flags take precedence over the file
+        let mut playlist = Playlist::load(matches.value_of("file").unwrap(), &presets)?;
+        if matches.is_present("shuffle") {
+            playlist.shuffle = true;
+        }
+        if matches.is_present("once") {
+            playlist.repeat = false;
+        }
+        if let Some(val) = matches.value_of("crossfade") {
+            playlist.crossfade = parse_duration(val).chain_err(|| "Invalid crossfade.")?;
         }
-        // Test whether the light command was specified
-        else if let Some(matches) = matches.subcommand_matches("light") {
-            // Fetch lights
-            let lights = client.fetch_lights().unwrap();
-
-            // Iterate over all lights
-            for (j, _) in lights.iter().enumerate() {
-                let mut batch = HueLightBatch::new(j + 1);
-                macro_rules! batch_op {
-                    ($fun:ident, $op:ident) => {
-                        if let Some(val) = matches.value_of(stringify!($op)) {
-                            batch.$fun(val.parse().unwrap());
-                        }
-                    };
-                }
-                batch_op!(brightness, bri);
-                batch_op!(saturation, sat);
-                batch_op!(hue, hue);
-                batch_op!(transition_time, tt);
-                let (light_i, params) = batch.build();
-                client.set_light_state(light_i, &params);
+
+        if options.show_params {
+            for entry in &playlist.entries {
+                let preset = entry.preset.as_deref();
+                let setup = EffectSetup::new(entry.effect, preset, Params::new(entry.effect.params), Vec::new(), &presets)?;
+                report_params(entry.effect.name, &setup.params, true);
+            }
+            return Ok(());
+        }
+
+        // Choose the lights and remember their states before the playlist starts
+        let lights = LightSelection::from_matches(matches)?.select(&home)?;
+        let snapshot = LightSnapshot::capture(&home, &lights);
+
+        // Play the entries, tuned from the terminal and the preset file
+        println!("Type 'help' for commands to tune the running effects.");
+        let commands = tuning_sources(&control, preset_path);
+        let reason = run_playlist(&playlist, presets.clone(), &home, &lights, &control, &options, commands)?;
+        leave_lights(&home, &snapshot, &lights, &options, reason)?;
+    }
+    // Test whether the light command was specified
+    else if let Some(matches) = matches.subcommand_matches("light") {
+
+        // Iterate over the selected lights
+        for uid in LightSelection::from_matches(matches)?.select(&home)? {
+            let light = home.light(&uid).unwrap();
+            let mut batch = HueLightBatch::new(light.get_id());
+            macro_rules! batch_op {
+                ($fun:ident, $op:ident) => {
+                    if let Some(val) = matches.value_of(stringify!($op)) {
+                        let val = val
+                            .parse()
+                            .chain_err(|| format!("Invalid value '{}' for --{}.", val, stringify!($op)))?;
+                        batch.$fun(val);
+                    }
+                };
+            }
+            batch_op!(brightness, bri);
+            batch_op!(saturation, sat);
+            batch_op!(hue, hue);
+            batch_op!(transition_time, tt);
+            let (_, params) = batch.build();
+            if let Err(err) = home.set_light_state(&uid, &params) {
+                println!("[Light {}] {}", uid, err);
             }
         }
     }
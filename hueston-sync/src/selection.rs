@@ -0,0 +1,272 @@
+use clap::{Arg, ArgMatches};
+use hueston::{HueHome, HueLight};
+use super::errors::*;
+
+/// A single light selector.
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+
+    /// Light ID on its bridge, written as `id:5` or `<bridge id>/id:5`
+    Id { bridge: Option<String>, id: usize },
+
+    /// Name pattern with `*` and `?` wildcards, or a unique ID
+    Name(String),
+}
+
+impl Selector {
+
+    /// Parse a comma-separated list of selectors.
+    fn parse_list(text: &str) -> Result<Vec<Selector>> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let (bridge, id) = if let Some(id) = part.strip_prefix("id:") {
+                    (None, id)
+                } else if let Some((bridge, id)) = part.split_once("/id:") {
+                    (Some(bridge.trim().to_string()), id)
+                } else {
+                    return Ok(Selector::Name(part.to_string()));
+                };
+                let id = id
+                    .trim()
+                    .parse()
+                    .chain_err(|| format!("Invalid light ID in '{}'.", part))?;
+                Ok(Selector::Id { bridge, id })
+            })
+            .collect()
+    }
+
+    /// Test whether a light of the given bridge matches the selector.
+    fn matches(&self, uid: &str, light: &HueLight, bridge_id: &str) -> bool {
+        match self {
+            Selector::Id { bridge, id } => {
+                let same_bridge = bridge.as_ref().is_none_or(|bridge| bridge.eq_ignore_ascii_case(bridge_id));
+                same_bridge && light.get_id() == *id
+            }
+            Selector::Name(pattern) => uid == pattern || glob_match(pattern, light.get_name()),
+        }
+    }
+
+    /// Make sure a light ID without a bridge names a single light.
+    ///
+    /// Light IDs are only unique per bridge.
+    fn check_unambiguous(&self, home: &HueHome) -> Result<()> {
+        let id = match self {
+            Selector::Id { bridge: None, id } => *id,
+            _ => return Ok(()),
+        };
+        let bridges: Vec<String> = home
+            .lights()
+            .filter(|(_, light)| light.get_id() == id)
+            .filter_map(|(uid, _)| home.light_client(uid))
+            .map(|client| client.get_bridge_id())
+            .collect();
+        if bridges.len() > 1 {
+            bail!(
+                "Light ID {} exists on several bridges, use e.g. {}/id:{} instead.",
+                id, bridges[0], id,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Case-insensitive match with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    // Greedy matching, backtracking to the last star
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Selection of lights for effects and commands.
+///
+/// Without `--lights` or `--group` all lights are selected. Models and
+/// exclusions narrow the selection down.
+#[derive(Debug, Clone, Default)]
+pub struct LightSelection {
+
+    /// Lights to include
+    lights: Vec<Selector>,
+
+    /// Name patterns of groups whose lights are included
+    groups: Vec<String>,
+
+    /// Lights to leave out
+    exclude: Vec<Selector>,
+
+    /// Model ID patterns the lights must match
+    models: Vec<String>,
+}
+
+/// Build the command-line arguments for selecting lights.
+pub fn selection_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("lights")
+            .short("l")
+            .long("lights")
+            .alias("light")
+            .takes_value(true)
+            .help("Choose lights by name pattern or ID, e.g. \"Living*,id:5\" or \"<bridge id>/id:5\""),
+        Arg::with_name("group")
+            .short("g")
+            .long("group")
+            .takes_value(true)
+            .help("Choose the lights of groups, e.g. \"Kitchen\""),
+        Arg::with_name("exclude")
+            .short("x")
+            .long("exclude")
+            .takes_value(true)
+            .help("Leave out lights by name pattern or ID"),
+        Arg::with_name("model")
+            .long("model")
+            .takes_value(true)
+            .help("Only choose lights of these models, e.g. LCT015"),
+    ]
+}
+
+impl LightSelection {
+
+    /// Parse the selection from command-line arguments.
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let split = |text: &str| -> Vec<String> {
+            text.split(',')
+                .map(str::trim)
+                .filter(|part| !part.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        Ok(Self {
+            lights: match matches.value_of("lights") {
+                Some(val) => Selector::parse_list(val)?,
+                None => Vec::new(),
+            },
+            groups: matches.value_of("group").map(split).unwrap_or_default(),
+            exclude: match matches.value_of("exclude") {
+                Some(val) => Selector::parse_list(val)?,
+                None => Vec::new(),
+            },
+            models: matches.value_of("model").map(split).unwrap_or_default(),
+        })
+    }
+
+    /// Parse a target naming groups or lights, e.g. `living` or `Bar*,id:7`.
+    ///
+    /// Group names take precedence, otherwise the target is
+    /// read like `--lights`.
+    pub fn from_target(home: &HueHome, target: &str) -> Result<Self> {
+        let is_group = home
+            .groups()
+            .any(|(_, group)| glob_match(target, group.get_name()));
+        if is_group {
+            return Ok(Self { groups: vec![target.to_string()], ..Self::default() });
+        }
+        let lights = Selector::parse_list(target)?;
+        if lights.is_empty() {
+            bail!("Invalid target '{}'.", target);
+        }
+        Ok(Self { lights, ..Self::default() })
+    }
+
+    /// Get the unique IDs of the selected lights.
+    ///
+    /// Fails if a group does not exist, a light ID is ambiguous
+    /// or no light is selected.
+    pub fn select(&self, home: &HueHome) -> Result<Vec<String>> {
+        for selector in self.lights.iter().chain(&self.exclude) {
+            selector.check_unambiguous(home)?;
+        }
+
+        // Collect the lights of the requested groups
+        let mut group_lights = Vec::new();
+        for pattern in &self.groups {
+            let uids: Vec<&String> = home
+                .groups()
+                .filter(|(_, group)| glob_match(pattern, group.get_name()))
+                .map(|(uid, _)| uid)
+                .collect();
+            if uids.is_empty() {
+                bail!("Unknown group '{}'.", pattern);
+            }
+            for uid in uids {
+                group_lights.extend(home.group_lights(uid).into_iter().cloned());
+            }
+        }
+
+        // Filter all lights, keeping the order of the home
+        let include_all = self.lights.is_empty() && self.groups.is_empty();
+        let bridge_id = |uid: &str| home.light_client(uid).map(|client| client.get_bridge_id()).unwrap_or_default();
+        let lights: Vec<String> = home
+            .lights()
+            .filter(|(uid, light)| {
+                include_all
+                    || group_lights.contains(uid)
+                    || self.lights.iter().any(|selector| selector.matches(uid, light, &bridge_id(uid)))
+            })
+            .filter(|(_, light)| {
+                self.models.is_empty()
+                    || self.models.iter().any(|model| glob_match(model, light.get_model_id()))
+            })
+            .filter(|(uid, light)| !self.exclude.iter().any(|selector| selector.matches(uid, light, &bridge_id(uid))))
+            .map(|(uid, _)| uid.clone())
+            .collect();
+        if lights.is_empty() {
+            bail!("No lights match the selection.");
+        }
+        Ok(lights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(glob_match("Living*", "Living Room"));
+        assert!(glob_match("living*", "LIVING ROOM"));
+        assert!(glob_match("*room", "Living Room"));
+        assert!(glob_match("L?ving*m", "Living Room"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("Living", "Living Room"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("a*b", "aXbYc"));
+    }
+
+    #[test]
+    fn parses_selectors() {
+        let selectors = Selector::parse_list(" Living*, id:5 ,,001788FFFE2A3B4C/id:7,00:17:88:01:03:2b:3c:4d-0b").unwrap();
+        assert_eq!(
+            selectors,
+            vec![
+                Selector::Name("Living*".to_string()),
+                Selector::Id { bridge: None, id: 5 },
+                Selector::Id { bridge: Some("001788FFFE2A3B4C".to_string()), id: 7 },
+                Selector::Name("00:17:88:01:03:2b:3c:4d-0b".to_string()),
+            ]
+        );
+        assert!(Selector::parse_list("").unwrap().is_empty());
+        assert!(Selector::parse_list("id:five").is_err());
+        assert!(Selector::parse_list("bridge/id:").is_err());
+    }
+}
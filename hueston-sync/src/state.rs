@@ -3,15 +3,23 @@ use std::fs::File;
 use super::errors::*;
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct BridgeConnectionInfo {
+#[serde(untagged)]
+pub enum BridgeConnectionInfo {
 
-    /// The bridge host.
-    /// This field is badly named, it's actually
-    /// a URL and not an IP address.
-    pub ip: String,
+    /// The cached bridge, including the username.
+    Cached(hueston::HueBridge),
 
-    /// The bridge username for authentication.
-    pub username: Option<String>,
+    /// A bridge saved by an older version, looked up once.
+    Legacy {
+
+        /// The bridge host.
+        /// This field is badly named, it's actually
+        /// a URL and not an IP address.
+        ip: String,
+
+        /// The bridge username for authentication.
+        username: Option<String>,
+    },
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -33,21 +41,54 @@ impl StartupState {
     }
 
     /// Connect bridge clients.
-    pub fn connect_bridge_clients(&self) -> Option<Vec<hueston::HueBridgeClient>> {
+    ///
+    /// Bridges with cached information are restored without network access.
+    /// Older entries are looked up once, their information is cached in the state.
+    /// With a record directory, the traffic of every bridge is recorded
+    /// to `<bridge id>.json` in that directory.
+    pub fn connect_bridge_clients(
+        &mut self,
+        builder: &hueston::HueBridgeClientBuilder,
+        record_dir: Option<&std::path::Path>,
+    ) -> Option<Vec<hueston::HueBridgeClient>> {
 
         // Make sure we found some bridges
-        let bridges = self.bridges.as_ref()?;
+        let bridges = self.bridges.as_mut()?;
 
         // Fetch bridge clients
         Some(bridges
-            .iter()
-            .map(|info| {
-                let username = info.username.as_ref().map(ToString::to_string);
-                let bridge = hueston::HueBridge::from_url(&info.ip);
-                bridge.map(|bridge| bridge.with_username(username))
+            .iter_mut()
+            .filter_map(|info| {
+
+                // Look up the bridge if it was not cached yet
+                let bridge = match info {
+                    BridgeConnectionInfo::Cached(bridge) => bridge.clone(),
+                    BridgeConnectionInfo::Legacy { ip, username } => match hueston::HueBridge::from_url(ip) {
+                        Ok(bridge) => bridge.with_username(username.clone()),
+                        Err(err) => {
+                            println!("Unable to reach bridge at {}: {}", ip, err);
+                            return None
+                        }
+                    },
+                };
+                *info = BridgeConnectionInfo::Cached(bridge.clone());
+
+                // Record the traffic of each bridge to its own cassette
+                let bridge_url = bridge.get_url().clone();
+                let mut builder = builder.clone();
+                if let Some(dir) = record_dir {
+                    builder = builder.record(dir.join(format!("{}.json", bridge.get_bridge_id())));
+                }
+
+                // Build the client
+                match builder.build(bridge) {
+                    Ok(client) => Some(client),
+                    Err(err) => {
+                        println!("Unable to create client for {}: {}", bridge_url, err);
+                        None
+                    }
+                }
             })
-            .filter_map(|bridge| bridge)
-            .map(hueston::HueBridgeClient::new)
             .collect()
         )
     }
@@ -61,6 +102,23 @@ impl StartupState {
         }
     }
 
+    /// Replace the stored info of a bridge with the same serial number.
+    pub fn update_bridge(&mut self, info: BridgeConnectionInfo) {
+        let serial = match &info {
+            BridgeConnectionInfo::Cached(bridge) => bridge.get_serial().clone(),
+            BridgeConnectionInfo::Legacy { .. } => return,
+        };
+        if let Some(bridges) = &mut self.bridges {
+            let existing = bridges.iter_mut().find(|existing| match existing {
+                BridgeConnectionInfo::Cached(bridge) => bridge.get_serial().eq_ignore_ascii_case(&serial),
+                BridgeConnectionInfo::Legacy { .. } => false,
+            });
+            if let Some(existing) = existing {
+                *existing = info;
+            }
+        }
+    }
+
     /// Test whether the state contains any bridges.
     pub fn has_bridges(&self) -> bool {
         self.bridges.is_some()
@@ -68,11 +126,34 @@ impl StartupState {
 
 }
 
-impl Into<BridgeConnectionInfo> for &hueston::HueBridgeClient {
-    fn into(self) -> BridgeConnectionInfo {
-        BridgeConnectionInfo {
-            ip: self.get_url().to_string(),
-            username: self.get_username().cloned(),
+impl From<&hueston::HueBridgeClient> for BridgeConnectionInfo {
+    fn from(client: &hueston::HueBridgeClient) -> Self {
+        BridgeConnectionInfo::Cached((**client).clone())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_legacy_and_cached_bridges() {
+        let legacy = r#"{"bridges":[{"ip":"http://192.168.1.2:80/","username":"secret"}]}"#;
+        let state: StartupState = serde_json::from_str(legacy).unwrap();
+        match &state.bridges.unwrap()[0] {
+            BridgeConnectionInfo::Legacy { ip, username } => {
+                assert_eq!(ip, "http://192.168.1.2:80/");
+                assert_eq!(username.as_deref(), Some("secret"));
+            }
+            other => panic!("Expected a legacy bridge, got {:?}", other),
+        }
+
+        // Cached bridges keep the username and survive a round trip
+        let bridge = hueston::HueBridge::new("http://192.168.1.2:80/", "001788fffe2a3b4c", "Hue", "BSB002", Some("secret"));
+        let state = StartupState { bridges: Some(vec![BridgeConnectionInfo::Cached(bridge.clone())]) };
+        let state: StartupState = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        match &state.bridges.unwrap()[0] {
+            BridgeConnectionInfo::Cached(cached) => assert_eq!(cached, &bridge),
+            other => panic!("Expected a cached bridge, got {:?}", other),
         }
     }
-} 
\ No newline at end of file
+}
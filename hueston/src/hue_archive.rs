@@ -0,0 +1,516 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use super::{HueBridgeClient, HueError};
+
+/// Current archive format version.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Attributes that can be set when creating a group.
+const GROUP_FIELDS: &[&str] = &["name", "type", "class", "lights"];
+
+/// Attributes that can be set when creating a scene.
+const SCENE_FIELDS: &[&str] = &[
+    "name",
+    "type",
+    "group",
+    "lights",
+    "recycle",
+    "appdata",
+    "picture",
+    "lightstates",
+];
+
+/// Attributes that can be set when creating a schedule.
+const SCHEDULE_FIELDS: &[&str] = &[
+    "name",
+    "description",
+    "command",
+    "localtime",
+    "status",
+    "autodelete",
+    "recycle",
+];
+
+/// Attributes that can be set when creating a rule.
+const RULE_FIELDS: &[&str] = &["name", "status", "recycle", "conditions", "actions"];
+
+/// Attributes that can be set when creating a sensor.
+const SENSOR_FIELDS: &[&str] = &[
+    "name",
+    "type",
+    "modelid",
+    "manufacturername",
+    "swversion",
+    "uniqueid",
+    "state",
+    "config",
+    "recycle",
+];
+
+/// Attributes that can be set when creating a resource link.
+const RESOURCELINK_FIELDS: &[&str] = &[
+    "name",
+    "description",
+    "type",
+    "classid",
+    "recycle",
+    "links",
+];
+
+/// Group types that are managed by the bridge and cannot be created.
+const IMPLICIT_GROUP_TYPES: &[&str] = &["Luminaire", "Lightsource"];
+
+/// Kinds of resources whose IDs are remapped on import.
+const MAPPED_KINDS: &[&str] = &[
+    "lights",
+    "sensors",
+    "groups",
+    "scenes",
+    "schedules",
+    "rules",
+    "resourcelinks",
+];
+
+/// Archived light.
+///
+/// Lights cannot be created, they are matched by their unique ID on import.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueArchivedLight {
+    pub name: String,
+    pub unique_id: String,
+}
+
+/// Archived physical sensor.
+///
+/// Physical sensors cannot be created, they are matched by their unique ID
+/// on import. Built-in sensors without a unique ID, like the daylight sensor,
+/// are matched by their type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueArchivedSensor {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub unique_id: Option<String>,
+}
+
+/// Portable archive of a bridge datastore.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueArchive {
+
+    /// Archive format version
+    pub version: u32,
+
+    /// ID of the bridge the archive was exported from
+    pub bridge_id: String,
+
+    /// Lights, by ID
+    pub lights: BTreeMap<String, HueArchivedLight>,
+
+    /// Groups, by ID
+    pub groups: BTreeMap<String, Value>,
+
+    /// Scenes including their light states, by ID
+    pub scenes: BTreeMap<String, Value>,
+
+    /// Schedules, by ID
+    pub schedules: BTreeMap<String, Value>,
+
+    /// Rules, by ID
+    pub rules: BTreeMap<String, Value>,
+
+    /// CLIP sensors, by ID
+    pub sensors: BTreeMap<String, Value>,
+
+    /// Physical sensors, by ID
+    #[serde(default)]
+    pub physical_sensors: BTreeMap<String, HueArchivedSensor>,
+
+    /// Resource links, by ID
+    pub resourcelinks: BTreeMap<String, Value>,
+}
+
+/// Result of an archive import.
+#[derive(Debug, Default)]
+pub struct HueImportReport {
+
+    /// Mapping from archived to new IDs, by resource kind
+    pub ids: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// Resources that could not be imported, by archived address
+    pub failures: Vec<(String, HueError)>,
+
+    /// References to resources missing on the bridge, by archived address
+    /// of the referencing resource. These references were left out.
+    pub dropped: Vec<(String, String)>,
+}
+
+impl HueImportReport {
+
+    /// Map an archived resource ID to the new ID.
+    pub fn map_id(&self, kind: &str, id: &str) -> Option<&String> {
+        self.ids.get(kind)?.get(id)
+    }
+
+    /// Record a new ID.
+    fn insert(&mut self, kind: &str, old: &str, new: String) {
+        self.ids
+            .entry(kind.to_string())
+            .or_default()
+            .insert(old.to_string(), new);
+    }
+
+    /// Map a reference to an archived resource to the new ID.
+    ///
+    /// Fails with the archived address if the resource is missing on the bridge.
+    /// Group 0 contains all lights and exists on every bridge.
+    fn map_ref(&self, kind: &str, id: &str) -> Result<String, String> {
+        if kind == "groups" && id == "0" {
+            return Ok(id.to_string());
+        }
+        self.map_id(kind, id).cloned().ok_or_else(|| format!("/{}/{}", kind, id))
+    }
+
+    /// Remap an address like `/api/<user>/groups/1/action`.
+    ///
+    /// The username is replaced. Fails with the archived address of the
+    /// referenced resource if it is missing on the bridge.
+    fn map_address(&self, address: &str, username: &str) -> Result<String, String> {
+        let mut parts: Vec<String> = address.split('/').map(ToString::to_string).collect();
+
+        // Replace the username
+        let offset = if parts.get(1).map(String::as_str) == Some("api") {
+            if parts.len() > 2 {
+                parts[2] = username.to_string();
+            }
+            3
+        } else {
+            1
+        };
+
+        // Replace the resource ID
+        if parts.len() > offset + 1 && MAPPED_KINDS.contains(&parts[offset].as_str()) {
+            parts[offset + 1] = self.map_ref(&parts[offset], &parts[offset + 1])?;
+        }
+
+        Ok(parts.join("/"))
+    }
+
+    /// Remap a list of IDs of the given kind.
+    ///
+    /// IDs missing on the bridge are left out and recorded for the resource.
+    fn map_ids(&mut self, address: &str, kind: &str, value: &Value) -> Value {
+        let ids = match value.as_array() {
+            Some(ids) => ids,
+            None => return value.clone(),
+        };
+        let mut mapped = Vec::new();
+        for id in ids {
+            match id.as_str().map(|id| self.map_ref(kind, id)) {
+                Some(Ok(new)) => mapped.push(Value::String(new)),
+                Some(Err(reference)) => self.dropped.push((address.to_string(), reference)),
+                None => mapped.push(id.clone()),
+            }
+        }
+        Value::Array(mapped)
+    }
+
+    /// Remap a list of addresses.
+    ///
+    /// Addresses of resources missing on the bridge are left out
+    /// and recorded for the resource.
+    fn map_addresses(&mut self, address: &str, value: &Value, username: &str) -> Value {
+        let addresses = match value.as_array() {
+            Some(addresses) => addresses,
+            None => return value.clone(),
+        };
+        let mut mapped = Vec::new();
+        for addr in addresses {
+            match addr.as_str().map(|addr| self.map_address(addr, username)) {
+                Some(Ok(new)) => mapped.push(Value::String(new)),
+                Some(Err(reference)) => self.dropped.push((address.to_string(), reference)),
+                None => mapped.push(addr.clone()),
+            }
+        }
+        Value::Array(mapped)
+    }
+
+    /// Remap the addresses and scene IDs of a list of commands or conditions.
+    fn map_commands(&self, value: &Value, username: &str) -> Result<Value, String> {
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| self.map_command(item, username))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            other => self.map_command(other, username),
+        }
+    }
+
+    /// Remap the address and scene ID of a single command or condition.
+    fn map_command(&self, value: &Value, username: &str) -> Result<Value, String> {
+        let mut value = value.clone();
+        if let Some(address) = value.get("address").and_then(Value::as_str).map(ToString::to_string) {
+            value["address"] = Value::String(self.map_address(&address, username)?);
+        }
+        if let Some(scene) = value.pointer("/body/scene").and_then(Value::as_str).map(ToString::to_string) {
+            value["body"]["scene"] = Value::String(self.map_ref("scenes", &scene)?);
+        }
+        Ok(value)
+    }
+
+    /// Record a resource that was skipped, since it references
+    /// a resource missing on the bridge.
+    fn skip(&mut self, address: String, reference: String) {
+        self.failures.push((address, HueError::UnknownResource(reference)));
+    }
+}
+
+/// Copy the given attributes of a resource.
+fn pick(value: &Value, fields: &[&str]) -> Map<String, Value> {
+    let mut map = Map::new();
+    for field in fields {
+        if let Some(val) = value.get(*field) {
+            map.insert(field.to_string(), val.clone());
+        }
+    }
+    map
+}
+
+impl HueBridgeClient {
+
+    /// Fetch all resources of a kind as raw JSON, by ID.
+    fn fetch_raw(&self, kind: &str) -> Result<BTreeMap<String, Value>, HueError> {
+        self.get(&self.get_user_endpoint(&format!("/{}", kind))?)
+    }
+
+    /// Export the bridge datastore into a portable archive.
+    ///
+    /// The archive contains light and sensor names, groups, scenes with their
+    /// light states, schedules, rules, CLIP sensors and resource links.
+    pub fn export_datastore(&self) -> Result<HueArchive, HueError> {
+
+        // Archive light names
+        let lights = self
+            .fetch_lights()?
+            .into_iter()
+            .map(|light| {
+                let archived = HueArchivedLight {
+                    name: light.get_name().clone(),
+                    unique_id: light.get_unique_id().clone(),
+                };
+                (light.get_id().to_string(), archived)
+            })
+            .collect();
+
+        // Scene lists lack the light states, fetch every scene on its own
+        let mut scenes = BTreeMap::new();
+        for id in self.fetch_raw("scenes")?.keys() {
+            let scene = self.get(&self.get_user_endpoint(&format!("/scenes/{}", id))?)?;
+            scenes.insert(id.clone(), scene);
+        }
+
+        // Only CLIP sensors can be recreated, physical ones are matched on import
+        let mut sensors = BTreeMap::new();
+        let mut physical_sensors = BTreeMap::new();
+        for (id, sensor) in self.fetch_raw("sensors")? {
+            let kind = sensor.get("type").and_then(Value::as_str).unwrap_or("");
+            if kind.starts_with("CLIP") {
+                sensors.insert(id, sensor);
+                continue;
+            }
+            let archived = HueArchivedSensor {
+                name: sensor.get("name").and_then(Value::as_str).unwrap_or("").to_string(),
+                r#type: kind.to_string(),
+                unique_id: sensor.get("uniqueid").and_then(Value::as_str).map(ToString::to_string),
+            };
+            physical_sensors.insert(id, archived);
+        }
+
+        Ok(HueArchive {
+            version: ARCHIVE_VERSION,
+            bridge_id: self.get_bridge_id(),
+            lights,
+            groups: self.fetch_raw("groups")?,
+            scenes,
+            schedules: self.fetch_raw("schedules")?,
+            rules: self.fetch_raw("rules")?,
+            sensors,
+            physical_sensors,
+            resourcelinks: self.fetch_raw("resourcelinks")?,
+        })
+    }
+
+    /// Import an archive into the bridge.
+    ///
+    /// Lights and physical sensors are matched by their unique ID and renamed,
+    /// all other resources are created anew. References between resources are
+    /// remapped to the new IDs. References to resources missing on the bridge
+    /// are left out of lists, and resources whose commands or conditions
+    /// depend on them are skipped. Both are collected in the report, along
+    /// with the resources that failed to import.
+    pub fn import_datastore(&self, archive: &HueArchive) -> Result<HueImportReport, HueError> {
+        if archive.version != ARCHIVE_VERSION {
+            return Err(HueError::UnsupportedArchive(archive.version));
+        }
+        let username = self.get_username().ok_or(HueError::NotRegistered)?.clone();
+        let mut report = HueImportReport::default();
+
+        // Match lights by their unique ID and restore their names
+        let lights = self.fetch_lights()?;
+        for (old_id, archived) in &archive.lights {
+            let light = match lights.iter().find(|light| light.get_unique_id() == &archived.unique_id) {
+                Some(light) => light,
+                None => {
+                    let err = HueError::UnknownResource(archived.unique_id.clone());
+                    report.failures.push((format!("/lights/{}", old_id), err));
+                    continue;
+                }
+            };
+            let new_id = light.get_id().to_string();
+            let url = self.get_user_endpoint(&format!("/lights/{}", new_id))?;
+            let body = serde_json::json!({ "name": archived.name });
+            if let Err(err) = self.put(&url, &body) {
+                report.failures.push((format!("/lights/{}", old_id), err));
+            }
+            report.insert("lights", old_id, new_id);
+        }
+
+        // Match physical sensors by their unique ID, or built-in ones by their type
+        let sensors = self.fetch_sensors()?;
+        for (old_id, archived) in &archive.physical_sensors {
+            let found = sensors.iter().find(|sensor| match &archived.unique_id {
+                Some(unique_id) => sensor.get_unique_id() == Some(unique_id),
+                None => sensor.get_unique_id().is_none() && sensor.get_type() == &archived.r#type,
+            });
+            let sensor = match found {
+                Some(sensor) => sensor,
+                None => {
+                    let name = archived.unique_id.as_ref().unwrap_or(&archived.r#type);
+                    let err = HueError::UnknownResource(name.clone());
+                    report.failures.push((format!("/sensors/{}", old_id), err));
+                    continue;
+                }
+            };
+            let new_id = sensor.get_id().to_string();
+            let url = self.get_user_endpoint(&format!("/sensors/{}", new_id))?;
+            let body = serde_json::json!({ "name": archived.name });
+            if let Err(err) = self.put(&url, &body) {
+                report.failures.push((format!("/sensors/{}", old_id), err));
+            }
+            report.insert("sensors", old_id, new_id);
+        }
+
+        // Create CLIP sensors
+        for (old_id, sensor) in &archive.sensors {
+            let mut body = pick(sensor, SENSOR_FIELDS);
+            if let Some(state) = body.get_mut("state").and_then(Value::as_object_mut) {
+                state.remove("lastupdated");
+            }
+            self.import_one(&mut report, "sensors", old_id, &body);
+        }
+
+        // Create groups
+        for (old_id, group) in &archive.groups {
+            let kind = group.get("type").and_then(Value::as_str).unwrap_or("");
+            if IMPLICIT_GROUP_TYPES.contains(&kind) {
+                continue;
+            }
+            let mut body = pick(group, GROUP_FIELDS);
+            if let Some(lights) = group.get("lights") {
+                let lights = report.map_ids(&format!("/groups/{}", old_id), "lights", lights);
+                body.insert("lights".to_string(), lights);
+            }
+            self.import_one(&mut report, "groups", old_id, &body);
+        }
+
+        // Create scenes
+        for (old_id, scene) in &archive.scenes {
+            let address = format!("/scenes/{}", old_id);
+            let mut body = pick(scene, SCENE_FIELDS);
+
+            // Group scenes derive their lights from the group
+            if let Some(group) = scene.get("group").and_then(Value::as_str) {
+                match report.map_ref("groups", group) {
+                    Ok(group) => body.insert("group".to_string(), Value::String(group)),
+                    Err(reference) => {
+                        report.skip(address, reference);
+                        continue;
+                    }
+                };
+                body.remove("lights");
+            } else if let Some(lights) = scene.get("lights") {
+                body.insert("lights".to_string(), report.map_ids(&address, "lights", lights));
+            }
+            if let Some(states) = scene.get("lightstates").and_then(Value::as_object) {
+                let mut mapped = Map::new();
+                for (id, state) in states {
+                    match report.map_ref("lights", id) {
+                        Ok(id) => mapped.insert(id, state.clone()),
+                        Err(reference) => {
+                            report.dropped.push((address.clone(), reference));
+                            continue;
+                        }
+                    };
+                }
+                body.insert("lightstates".to_string(), Value::Object(mapped));
+            }
+            self.import_one(&mut report, "scenes", old_id, &body);
+        }
+
+        // Create schedules
+        for (old_id, schedule) in &archive.schedules {
+            let mut body = pick(schedule, SCHEDULE_FIELDS);
+            if let Some(command) = schedule.get("command") {
+                match report.map_command(command, &username) {
+                    Ok(command) => body.insert("command".to_string(), command),
+                    Err(reference) => {
+                        report.skip(format!("/schedules/{}", old_id), reference);
+                        continue;
+                    }
+                };
+            }
+            self.import_one(&mut report, "schedules", old_id, &body);
+        }
+
+        // Create rules
+        'rules: for (old_id, rule) in &archive.rules {
+            let mut body = pick(rule, RULE_FIELDS);
+
+            // Leaving out a condition would make the rule trigger more often
+            for key in &["conditions", "actions"] {
+                if let Some(items) = rule.get(*key) {
+                    match report.map_commands(items, &username) {
+                        Ok(items) => body.insert(key.to_string(), items),
+                        Err(reference) => {
+                            report.skip(format!("/rules/{}", old_id), reference);
+                            continue 'rules;
+                        }
+                    };
+                }
+            }
+            self.import_one(&mut report, "rules", old_id, &body);
+        }
+
+        // Create resource links
+        for (old_id, link) in &archive.resourcelinks {
+            let mut body = pick(link, RESOURCELINK_FIELDS);
+            if let Some(links) = link.get("links") {
+                let links = report.map_addresses(&format!("/resourcelinks/{}", old_id), links, &username);
+                body.insert("links".to_string(), links);
+            }
+            self.import_one(&mut report, "resourcelinks", old_id, &body);
+        }
+
+        Ok(report)
+    }
+
+    /// Create a single resource and record its new ID in the report.
+    fn import_one(&self, report: &mut HueImportReport, kind: &str, old_id: &str, body: &Map<String, Value>) {
+        match self.create(&format!("/{}", kind), body) {
+            Ok(new_id) => report.insert(kind, old_id, new_id),
+            Err(err) => report.failures.push((format!("/{}/{}", kind, old_id), err)),
+        }
+    }
+}
\ No newline at end of file
@@ -1,35 +1,95 @@
 use std::io::Read;
 
-use ssdp::header::{HeaderRef, HeaderMut};
-use ssdp::message::{SearchRequest, Multicast};
-
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use serde_xml_rs::deserialize;
 
+use super::HueError;
+use super::hue_profile::BridgeProfile;
+use super::hue_discovery::{self, DiscoveryEvent, DiscoveryOptions};
+
 /// Hue Bridge Device.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct HueBridgeDevice {
     #[serde(rename = "friendlyName")]
     friendly_name: String,
+    #[serde(default)]
+    manufacturer: String,
     #[serde(rename = "modelName")]
     model_name: String,
     #[serde(rename = "serialNumber")]
     serial_number: String,
-    #[serde(rename = "UDN")]
+    #[serde(rename = "UDN", default)]
     udn: String,
 }
 
 /// Hue Bridge.
-#[derive(Deserialize, Debug)]
+///
+/// A bridge can be serialized and deserialized again,
+/// which allows restoring it from a cache without network access.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct HueBridge {
     #[serde(rename = "URLBase")]
     url_base: String,
     device: HueBridgeDevice,
     username: Option<String>,
+    #[serde(default)]
+    profile: BridgeProfile,
+}
+
+impl HueBridgeDevice {
+
+    /// Get the friendly name.
+    pub fn get_friendly_name(&self) -> &String {
+        &self.friendly_name
+    }
+
+    /// Get the manufacturer.
+    pub fn get_manufacturer(&self) -> &String {
+        &self.manufacturer
+    }
+
+    /// Get the model name.
+    pub fn get_model_name(&self) -> &String {
+        &self.model_name
+    }
+
+    /// Get the serial number.
+    pub fn get_serial_number(&self) -> &String {
+        &self.serial_number
+    }
+
+    /// Get the unique device name.
+    pub fn get_udn(&self) -> &String {
+        &self.udn
+    }
 }
 
 impl HueBridge {
 
+    /// Construct a `HueBridge` from cached connection info.
+    ///
+    /// This does not perform any network I/O, use
+    /// `HueBridgeClient::check_alive` to test whether the bridge responds.
+    pub fn new(url: &str, serial: &str, name: &str, model: &str, username: Option<&str>) -> Self {
+        Self {
+            url_base: url.to_string(),
+            device: HueBridgeDevice {
+                friendly_name: name.to_string(),
+                manufacturer: String::new(),
+                model_name: model.to_string(),
+                serial_number: serial.to_string(),
+                udn: String::new(),
+            },
+            username: username.map(ToString::to_string),
+            profile: BridgeProfile::Philips,
+        }
+    }
+
+    /// Get the device description.
+    pub fn get_device(&self) -> &HueBridgeDevice {
+        &self.device
+    }
+
     /// Get the friendly name.
     pub fn get_name(&self) -> &String {
         &self.device.friendly_name
@@ -40,11 +100,55 @@ impl HueBridge {
         &self.url_base
     }
 
+    /// Set the base URL.
+    pub fn set_url(&mut self, url: String) {
+        self.url_base = url;
+    }
+
     /// Get the model name.
     pub fn get_model(&self) -> &String {
         &self.device.model_name
     }
 
+    /// Get the manufacturer.
+    pub fn get_manufacturer(&self) -> &String {
+        &self.device.manufacturer
+    }
+
+    /// Get the kind of gateway.
+    pub fn get_profile(&self) -> BridgeProfile {
+        self.profile
+    }
+
+    /// Set the kind of gateway.
+    pub fn set_profile(&mut self, profile: BridgeProfile) {
+        self.profile = profile;
+    }
+
+    /// Set the kind of gateway.
+    pub fn with_profile(mut self, profile: BridgeProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Get the serial number.
+    pub fn get_serial(&self) -> &String {
+        &self.device.serial_number
+    }
+
+    /// Get the bridge ID.
+    ///
+    /// The bridge ID is derived from the serial number
+    /// and matches the `bridgeid` of the bridge configuration.
+    pub fn get_bridge_id(&self) -> String {
+        let serial = self.device.serial_number.to_uppercase();
+        if serial.len() == 12 && serial.is_ascii() {
+            format!("{}{}{}", &serial[..6], self.profile.bridge_id_infix(), &serial[6..])
+        } else {
+            serial
+        }
+    }
+
     /// Get the username.
     pub fn get_username(&self) -> Option<&String> {
         self.username.as_ref()
@@ -77,78 +181,44 @@ impl HueBridge {
     /// Discover Hue Bridge devices using various methods.
     /// 
     /// At the moment, only UPnP discovery is supported.
-    pub fn discover_all() -> Option<Vec<HueBridge>> {
-        Self::discover_upnp()
+    pub fn discover_all() -> Result<Vec<HueBridge>, HueError> {
+        Self::discover_with(&DiscoveryOptions::default(), |_| ())
     }
 
-    /// Discover Hue Bridge devices via UPnP.
-    fn discover_upnp() -> Option<Vec<HueBridge>> {
-
-        // Create a vector of hue bridges
-        let mut vec = Vec::<HueBridge>::new();
-
-        // Create a set of hue bridge locations
-        let mut found = std::collections::HashSet::<String>::new();
-
-        // Create the UPnP search request
-        let mut req = SearchRequest::new();
-        req.set(ssdp::header::Man);
-        req.set(ssdp::header::MX(5));
-
-        let mut process_response = |msg: ssdp::message::SearchResponse| -> Option<HueBridge> {
-
-            // Extract the server header
-            let server = msg.get::<ssdp::header::Server>()?;
-
-            // Test whether the server contains "IpBridge"
-            if !server.as_str().contains("IpBridge") { return None }
-
-            // Extract the location header
-            let location = msg
-                .get::<ssdp::header::Location>()?
-                .as_str()
-                .to_string();
-            
-            // Continue if the location was already found
-            if found.contains(&location) { return None }
-
-            // Fetch the bridge configuration
-            let mut resp = reqwest::get(&location).ok()?;
-
-            // Insert the location into the set
-            found.insert(location);
-
-            // Read the bridge configuration into a string
-            let mut content = String::new();
-            resp.read_to_string(&mut content).ok()?;
-
-            // Deserialize the bridge configuration
-            deserialize(content.as_bytes()).ok()?
-        };
-
-        // Iterate over all search responses
-        for (msg, _) in req.multicast().unwrap() {
-            if let Some(bridge) = process_response(msg) {
-                vec.push(bridge);
-            }
-        }
-
-        // Return the discovered bridges
-        if vec.is_empty() { None } else { Some(vec) }
+    /// Discover Hue Bridge devices with custom options.
+    ///
+    /// The callback is invoked for every bridge as soon as it is found,
+    /// and for every bridge whose description could not be fetched.
+    pub fn discover_with<F>(options: &DiscoveryOptions, on_event: F) -> Result<Vec<HueBridge>, HueError>
+    where
+        F: FnMut(DiscoveryEvent),
+    {
+        hue_discovery::discover_upnp(options, on_event)
     }
 
-    pub fn from_url(url: &str) -> Option<HueBridge> {
+    /// Fetch the bridge description from a base URL.
+    pub fn from_url(url: &str) -> Result<HueBridge, HueError> {
         let url = &format!("{}description.xml", url);
-        
+        Self::fetch_description(&reqwest::Client::new(), url)
+    }
+
+    /// Fetch and deserialize a bridge description.
+    pub(crate) fn fetch_description(client: &reqwest::Client, url: &str) -> Result<HueBridge, HueError> {
+
         // Fetch the bridge configuration
-        let mut resp = reqwest::get(url).ok()?;
+        let mut resp = client.get(url).send()?.error_for_status()?;
 
         // Read the bridge configuration into a string
         let mut content = String::new();
-        resp.read_to_string(&mut content).ok()?;
+        resp.read_to_string(&mut content)?;
 
         // Deserialize the bridge configuration
-        deserialize(content.as_bytes()).ok()?
+        let mut bridge: HueBridge = deserialize(content.as_bytes()).map_err(|err| HueError::Xml(err.to_string()))?;
+
+        // Guess the kind of gateway, compatible ones often mimic Philips
+        let device = &bridge.device;
+        bridge.profile = BridgeProfile::from_description(&device.manufacturer, &device.model_name, &device.friendly_name);
+        Ok(bridge)
     }
 
     pub fn with_username(mut self, username: Option<String>) -> Self {
@@ -1,94 +1,540 @@
 use super::HueBridge;
+use super::HueConfig;
+use super::HueError;
+use super::DiscoveryOptions;
+use super::HueGroup;
 use super::HueLight;
+use super::HueSensor;
 use super::hue_resp::{
     HueErrorCode,
+    HueBridgePublicConfig,
     HueBridgeRegistration,
+    HueBridgeStatus,
 };
+use super::hue_profile::{bridge_ids_match, BridgeProfile};
+use super::hue_features::{API_INCREMENTS, API_RESOURCELINKS, BridgeFeatures, HueResourceKind};
+use super::hue_retry::RetryPolicy;
+use super::hue_cassette::{cassette_path, HueCassette, HueInteraction, Transport};
 
 use maplit::hashmap;
-use std::collections::BTreeMap;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Outcome of a bridge relocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Relocation {
+
+    /// The bridge is still reachable at its known address.
+    Unchanged,
+
+    /// The bridge was found at a new address.
+    Moved {
+        old_url: String,
+        new_url: String,
+    },
+}
+
+/// Builder for `HueBridgeClient`.
+#[derive(Debug, Clone, Default)]
+pub struct HueBridgeClientBuilder {
+
+    /// Timeout for establishing a connection.
+    connect_timeout: Option<Duration>,
+
+    /// Timeout for a whole request, including reading the response.
+    timeout: Option<Duration>,
+
+    /// Retry policy for failed requests.
+    retry: RetryPolicy,
+
+    /// Cassette to record to or replay from.
+    cassette: Option<CassetteMode>,
+}
+
+/// Cassette usage of a `HueBridgeClientBuilder`.
+#[derive(Debug, Clone)]
+enum CassetteMode {
+    Record(PathBuf),
+    Replay(HueCassette),
+}
+
+impl HueBridgeClientBuilder {
+
+    /// Set the timeout for establishing a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for a whole request, including reading the response.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the retry policy.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Record all requests and responses to a cassette file.
+    ///
+    /// The file is written when the client is dropped,
+    /// see `HueBridgeClient::flush_cassette`.
+    pub fn record<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.cassette = Some(CassetteMode::Record(path.into()));
+        self
+    }
+
+    /// Serve responses from a cassette instead of talking to the bridge.
+    pub fn replay(mut self, cassette: HueCassette) -> Self {
+        self.cassette = Some(CassetteMode::Replay(cassette));
+        self
+    }
+
+    /// Build a `HueBridgeClient` for a `HueBridge`.
+    pub fn build(self, bridge: HueBridge) -> Result<HueBridgeClient, HueError> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let transport = match self.cassette {
+            Some(CassetteMode::Record(path)) => Transport::record(&bridge, path),
+            Some(CassetteMode::Replay(cassette)) => Transport::replay(cassette),
+            None => Transport::Live,
+        };
+        Ok(HueBridgeClient {
+            bridge,
+            client: builder.build()?,
+            retry: self.retry,
+            features: None,
+            created: Mutex::new(HashMap::new()),
+            transport,
+        })
+    }
+}
 
 /// Hue Bridge.
 #[derive(Debug)]
 pub struct HueBridgeClient {
     bridge: HueBridge,
     client: reqwest::Client,
+    retry: RetryPolicy,
+    features: Option<BridgeFeatures>,
+    created: Mutex<HashMap<HueResourceKind, u32>>,
+    transport: Transport,
 }
 
 impl HueBridgeClient {
 
     /// Construct a new `HueBridgeClient` from a `HueBridge`.
+    ///
+    /// The client uses the default retry policy, see `HueBridgeClient::builder`
+    /// for configuring timeouts and retries.
     pub fn new(bridge: HueBridge) -> Self {
         Self {
             bridge,
-            client: reqwest::Client::new()
+            client: reqwest::Client::new(),
+            retry: RetryPolicy::default(),
+            features: None,
+            created: Mutex::new(HashMap::new()),
+            transport: Transport::Live,
         }
     }
 
+    /// Construct a `HueBridgeClient` replaying a cassette file.
+    ///
+    /// The client talks to the bridge the cassette was recorded from,
+    /// see `HueBridgeClientBuilder::record`.
+    pub fn from_cassette<P: AsRef<Path>>(path: P) -> Result<Self, HueError> {
+        let cassette = HueCassette::load(path)?;
+        let bridge = cassette.get_bridge().clone();
+        Self::builder().retry_policy(RetryPolicy::none()).replay(cassette).build(bridge)
+    }
+
+    /// Write the recorded traffic to the cassette file.
+    ///
+    /// Recorded traffic is also written when the client is dropped,
+    /// but errors can only be handled here.
+    pub fn flush_cassette(&self) -> Result<(), HueError> {
+        self.transport.flush()
+    }
+
+    /// Construct a `HueBridgeClientBuilder`.
+    pub fn builder() -> HueBridgeClientBuilder {
+        HueBridgeClientBuilder::default()
+    }
+
     /// Get the API username
     pub fn get_username(&self) -> Option<&String> {
         self.bridge.get_username()
     }
 
-    pub fn set_light_saturation(&self, i: usize, sat: u8) {
-        let username = &self.get_username().unwrap();
-        let ep = self.get_auth_endpoint(username, &format!("/lights/{}/state", i));
+    /// Detect the features supported by the bridge.
+    ///
+    /// Once detected, calls the bridge does not support are refused
+    /// with `HueError::Unsupported` instead of being sent. Detecting
+    /// again replaces the features, e.g. after a firmware update.
+    pub fn detect_features(&mut self) -> Result<&BridgeFeatures, HueError> {
+        let features = self.fetch_features()?;
+        self.created.lock().unwrap().clear();
+        Ok(self.features.insert(features))
+    }
+
+    /// Detect the kind of gateway from its public configuration.
+    ///
+    /// The detected profile replaces the one guessed from the description.
+    pub fn detect_profile(&mut self) -> Result<BridgeProfile, HueError> {
+        let config: HueBridgePublicConfig = self.get(&self.get_endpoint("/config"))?;
+        let model_id = config.model_id.as_deref();
+        let profile = BridgeProfile::from_config(model_id, &config.name);
+        self.bridge.set_profile(profile);
+        Ok(profile)
+    }
+
+    /// Get the detected bridge features.
+    pub fn get_features(&self) -> Option<&BridgeFeatures> {
+        self.features.as_ref()
+    }
+
+    /// Make sure the bridge supports the given state attributes.
+    fn check_state_support(&self, state: &HashMap<&'static str, Value>) -> Result<(), HueError> {
+        let features = match &self.features {
+            Some(features) => features,
+            None => return Ok(()),
+        };
+        if !features.supports_increments() {
+            if let Some(key) = state.keys().find(|key| key.ends_with("_inc")) {
+                return Err(HueError::Unsupported {
+                    feature: key.to_string(),
+                    required: API_INCREMENTS,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure a resource of the given kind can be created.
+    ///
+    /// Resources created since the features were detected are taken
+    /// into account, deleted ones are not.
+    fn check_create_support(&self, ep: &str) -> Result<(), HueError> {
+        let features = match &self.features {
+            Some(features) => features,
+            None => return Ok(()),
+        };
+        let kind = match resource_kind(ep) {
+            Some(kind) => kind,
+            None => return Ok(()),
+        };
+        if kind == HueResourceKind::ResourceLinks && !features.supports_resourcelinks() {
+            return Err(HueError::Unsupported {
+                feature: kind.to_string(),
+                required: API_RESOURCELINKS,
+            });
+        }
+        let created = self.created.lock().unwrap().get(&kind).cloned().unwrap_or(0);
+        if !features.fits(kind, created + 1) {
+            return Err(HueError::CapacityExceeded(kind));
+        }
+        Ok(())
+    }
+
+    /// Get an authenticated API endpoint for the registered user.
+    pub(crate) fn get_user_endpoint(&self, ep: &str) -> Result<String, HueError> {
+        let username = self.get_username().ok_or(HueError::NotRegistered)?;
+        Ok(self.get_auth_endpoint(username, ep))
+    }
+
+    /// Send a request, retrying it according to the retry policy.
+    ///
+    /// Errors reported by the bridge in the response body
+    /// are returned as `HueError::Bridge`. POST requests are not
+    /// retried after transport errors, since they create resources.
+    pub(crate) fn request(&self, method: Method, url: &str, body: Option<&Value>) -> Result<Value, HueError> {
+        self.request_with(method, url, body, &self.retry)
+    }
+
+    /// Send a request, retrying it according to the given retry policy.
+    fn request_with(&self, method: Method, url: &str, body: Option<&Value>, retry: &RetryPolicy) -> Result<Value, HueError> {
+        let idempotent = method != Method::POST;
+        let mut attempt = 1;
+        loop {
+            match self.request_once(method.clone(), url, body) {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    if !retry.should_retry(attempt, &err, idempotent) {
+                        return Err(err);
+                    }
+                    std::thread::sleep(retry.delay(attempt));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Send a request once.
+    fn request_once(&self, method: Method, url: &str, body: Option<&Value>) -> Result<Value, HueError> {
+
+        // Make sure the request succeeded
+        let (status, value) = self.exchange(method, url, body, None)?;
+        if !(200..300).contains(&status) {
+            return Err(HueError::Status(status));
+        }
+
+        // Check the response for bridge errors
+        match HueBridgeStatus::first_error(&value) {
+            Some(err) => Err(err),
+            None => Ok(value),
+        }
+    }
+
+    /// Exchange a request and its response through the transport.
+    ///
+    /// Returns the HTTP status code and the response body.
+    fn exchange(&self, method: Method, url: &str, body: Option<&Value>, auth: Option<(&str, &str)>) -> Result<(u16, Value), HueError> {
+        if let Transport::Live = self.transport {
+            return self.send(method, url, body, auth);
+        }
+
+        // Replay or record the exchange
+        let path = cassette_path(&self.bridge, url);
+        if let Transport::Replay { .. } = self.transport {
+            return self.transport.replay_interaction(method.as_str(), &path, body);
+        }
+        let (status, response) = self.send(method.clone(), url, body, auth)?;
+        self.transport.record_interaction(HueInteraction {
+            method: method.as_str().to_string(),
+            path,
+            body: body.cloned(),
+            status,
+            response: response.clone(),
+        });
+        Ok((status, response))
+    }
+
+    /// Send a request to the bridge.
+    ///
+    /// Successful responses must be JSON, others are returned as a string.
+    fn send(&self, method: Method, url: &str, body: Option<&Value>, auth: Option<(&str, &str)>) -> Result<(u16, Value), HueError> {
+        let mut req = self.client.request(method, url);
+        if let Some(body) = body {
+            req = req.json(body);
+        }
+        if let Some((login, password)) = auth {
+            req = req.basic_auth(login, Some(password));
+        }
+
+        // Read the response
+        let mut res = req.send()?;
+        let status = res.status();
+        let text = res.text()?;
+        let value = if status.is_success() {
+            serde_json::from_str(&text)?
+        } else {
+            serde_json::from_str(&text).unwrap_or(Value::String(text))
+        };
+        Ok((status.as_u16(), value))
+    }
+
+    /// Fetch and deserialize a resource.
+    pub(crate) fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, HueError> {
+        let value = self.request(Method::GET, url, None)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Update a resource.
+    pub(crate) fn put<T: Serialize + ?Sized>(&self, url: &str, body: &T) -> Result<Value, HueError> {
+        let body = serde_json::to_value(body)?;
+        self.request(Method::PUT, url, Some(&body))
+    }
+
+    /// Create a resource.
+    pub(crate) fn post<T: Serialize + ?Sized>(&self, url: &str, body: &T) -> Result<Value, HueError> {
+        let body = serde_json::to_value(body)?;
+        self.request(Method::POST, url, Some(&body))
+    }
+
+    /// Delete a resource.
+    pub(crate) fn delete(&self, url: &str) -> Result<Value, HueError> {
+        self.request(Method::DELETE, url, None)
+    }
+
+    /// Create a resource and return the ID assigned by the bridge.
+    pub(crate) fn create<T: Serialize + ?Sized>(&self, ep: &str, body: &T) -> Result<String, HueError> {
+        self.check_create_support(ep)?;
+        let url = self.get_user_endpoint(ep)?;
+        let value = self.post(&url, body)?;
+        let id = HueBridgeStatus::created_id(&value).ok_or(HueError::UnexpectedResponse(value))?;
+
+        // Account for the used capacity
+        if let Some(kind) = resource_kind(ep) {
+            *self.created.lock().unwrap().entry(kind).or_insert(0) += 1;
+        }
+        Ok(id)
+    }
+
+    /// Test whether the bridge responds and is still the same bridge.
+    pub fn check_alive(&self) -> Result<(), HueError> {
+        let config: HueBridgePublicConfig = self.get(&self.get_endpoint("/config"))?;
+
+        // Make sure no other bridge took over the address
+        let expected = self.get_bridge_id();
+        if !bridge_ids_match(&config.bridge_id, &expected) {
+            return Err(HueError::BridgeMismatch {
+                expected,
+                found: config.bridge_id,
+            });
+        }
+        Ok(())
+    }
+
+    /// Make sure the bridge is reachable, relocate it otherwise.
+    ///
+    /// Returns `Relocation::Moved` if the bridge address changed,
+    /// so the caller can persist the new address.
+    pub fn ensure_alive(&mut self, options: &DiscoveryOptions) -> Result<Relocation, HueError> {
+        match self.check_alive() {
+            Ok(()) => Ok(Relocation::Unchanged),
+            Err(_) => self.relocate(options),
+        }
+    }
+
+    /// Rediscover the bridge by its serial number and update its base URL.
+    pub fn relocate(&mut self, options: &DiscoveryOptions) -> Result<Relocation, HueError> {
+        let bridges = HueBridge::discover_with(options, |_| ())?;
+
+        // Find the bridge with a matching serial number
+        let found = bridges
+            .into_iter()
+            .find(|bridge| bridge.get_serial().eq_ignore_ascii_case(self.get_serial()))
+            .ok_or_else(|| HueError::BridgeNotFound(self.get_bridge_id()))?;
+
+        // Test whether the address changed
+        let old_url = self.get_url().to_string();
+        let new_url = found.get_url().to_string();
+        if old_url == new_url {
+            return Ok(Relocation::Unchanged);
+        }
+
+        // Update the base URL
+        self.bridge.set_url(new_url.clone());
+        Ok(Relocation::Moved { old_url, new_url })
+    }
+
+    pub fn set_light_saturation(&self, i: usize, sat: u8) -> Result<(), HueError> {
+        let ep = self.get_user_endpoint(&format!("/lights/{}/state", i))?;
         let params = hashmap!{
             "sat" => sat.max(1).min(254)
         };
-        let req = self.client.put(&ep).json(&params);
-        let mut res = req.send().ok().unwrap();
-        let data = res.text().ok().unwrap();
-        println!("{:?}", data);
+        self.put(&ep, &params)?;
+        Ok(())
+    }
+
+    pub fn set_light_state(&self, i: usize, state: &HashMap<&'static str, Value>) -> Result<(), HueError> {
+        self.check_state_support(state)?;
+        let ep = self.get_user_endpoint(&format!("/lights/{}/state", i))?;
+        self.put(&ep, state)?;
+        Ok(())
+    }
+
+    /// Set the state of a light without retrying.
+    ///
+    /// For callers that send states continuously and pick up failed
+    /// changes with the next state, so an unreachable bridge does not
+    /// hold them up for the whole retry policy.
+    pub fn set_light_state_once(&self, i: usize, state: &HashMap<&'static str, Value>) -> Result<(), HueError> {
+        self.check_state_support(state)?;
+        let ep = self.get_user_endpoint(&format!("/lights/{}/state", i))?;
+        let body = serde_json::to_value(state)?;
+        self.request_with(Method::PUT, &ep, Some(&body), &RetryPolicy::none())?;
+        Ok(())
+    }
+
+    pub fn set_light_state_str(&self, i: usize, state: &str) -> Result<(), HueError> {
+        let ep = self.get_user_endpoint(&format!("/lights/{}/state", i))?;
+        let json: Value = serde_json::from_str(state)?;
+        self.put(&ep, &json)?;
+        Ok(())
+    }
+
+    pub fn fetch_lights(&self) -> Result<Vec<HueLight>, HueError> {
+        let ep = self.get_user_endpoint("/lights")?;
+        let hm: BTreeMap<usize, HueLight> = self.get(&ep)?;
+        Ok(hm.into_iter().map(|(k, mut v)| { v.set_id(k); v }).collect())
+    }
+
+    /// Fetch all groups.
+    pub fn fetch_groups(&self) -> Result<Vec<HueGroup>, HueError> {
+        let ep = self.get_user_endpoint("/groups")?;
+        let hm: BTreeMap<usize, HueGroup> = self.get(&ep)?;
+        Ok(hm.into_iter().map(|(k, mut v)| { v.set_id(k); v }).collect())
     }
 
-    pub fn set_light_state(&self, i: usize, state: &std::collections::HashMap<&'static str, serde_json::Value>) {
-        let username = &self.get_username().unwrap();
-        let ep = self.get_auth_endpoint(username, &format!("/lights/{}/state", i));
-        let req = self.client.put(&ep).json(&state);
-        let mut res = req.send().ok().unwrap();
-        let data = res.text().ok().unwrap();
+    /// Fetch the bridge configuration.
+    pub fn fetch_config(&self) -> Result<HueConfig, HueError> {
+        self.get(&self.get_user_endpoint("/config")?)
     }
 
-    pub fn set_light_state_str(&self, i: usize, state: &str) {
-        let username = &self.get_username().unwrap();
-        let ep = self.get_auth_endpoint(username, &format!("/lights/{}/state", i));
-        let json: serde_json::Value = serde_json::from_str(state).unwrap();
-        let req = self.client.put(&ep).json(&json);
-        let mut res = req.send().ok().unwrap();
-        let data = res.text().ok().unwrap();
+    /// Fetch all sensors.
+    pub fn fetch_sensors(&self) -> Result<Vec<HueSensor>, HueError> {
+        let ep = self.get_user_endpoint("/sensors")?;
+        let hm: BTreeMap<usize, HueSensor> = self.get(&ep)?;
+        Ok(hm.into_iter().map(|(k, mut v)| { v.set_id(k); v }).collect())
     }
 
-    pub fn fetch_lights(&self) -> Option<Vec<HueLight>> {
-        let username = &self.get_username()?;
-        let ep = self.get_auth_endpoint(username, "/lights");
-        let req = self.client.get(&ep);
-        let mut res = req.send().ok()?;
-        let hm: BTreeMap<usize, HueLight> = res.json().ok()?;
-        Some(hm.into_iter().map(|(_, v)| v).collect())
+    /// Set the state of all lights in a group.
+    pub fn set_group_action(&self, i: usize, action: &HashMap<&'static str, Value>) -> Result<(), HueError> {
+        self.check_state_support(action)?;
+        let ep = self.get_user_endpoint(&format!("/groups/{}/action", i))?;
+        self.put(&ep, action)?;
+        Ok(())
     }
 
     pub fn register(&mut self, app: &str) -> Result<(), HueErrorCode> {
+        self.send_registration(app, None)
+    }
+
+    /// Register with the gateway credentials instead of the link button.
+    ///
+    /// Only deCONZ gateways support this, using the login
+    /// and password of the Phoscon app.
+    pub fn register_with_credentials(&mut self, app: &str, login: &str, password: &str) -> Result<(), HueErrorCode> {
+        if self.bridge.get_profile() != BridgeProfile::Deconz {
+            return Err(HueErrorCode::Unknown);
+        }
+        self.send_registration(app, Some((login, password)))
+    }
+
+    /// Send the registration request and store the username.
+    fn send_registration(&mut self, app: &str, auth: Option<(&str, &str)>) -> Result<(), HueErrorCode> {
 
         // Get the API endpoint
         let ep = self.get_endpoint("");
 
         // Build parameters
-        let params = hashmap! {
-            "devicetype" => format!("Hueston#{}", app),
-        };
-
-        // Prepare POST request
-        let req = self.client.post(&ep).json(&params);
+        let params = serde_json::json!({
+            "devicetype": format!("Hueston#{}", app),
+        });
 
         // Send request
-        let mut res = match req.send() {
+        let (_, value) = match self.exchange(Method::POST, &ep, Some(&params), auth) {
             Ok(res) => res,
             Err(_) => return Err(HueErrorCode::Unknown),
         };
 
         // Deserialize response
-        let data: Vec<HueBridgeRegistration> = match res.json() {
+        let data: Vec<HueBridgeRegistration> = match serde_json::from_value(value) {
             Ok(data) => data,
             Err(err) => {
                 println!("{}", err);
@@ -115,6 +561,20 @@ impl HueBridgeClient {
     }
 }
 
+/// Get the kind of resources created at an endpoint like `/groups`.
+fn resource_kind(ep: &str) -> Option<HueResourceKind> {
+    match ep.trim_matches('/') {
+        "lights" => Some(HueResourceKind::Lights),
+        "sensors" => Some(HueResourceKind::Sensors),
+        "groups" => Some(HueResourceKind::Groups),
+        "scenes" => Some(HueResourceKind::Scenes),
+        "rules" => Some(HueResourceKind::Rules),
+        "schedules" => Some(HueResourceKind::Schedules),
+        "resourcelinks" => Some(HueResourceKind::ResourceLinks),
+        _ => None,
+    }
+}
+
 impl std::ops::Deref for HueBridgeClient {
     type Target = HueBridge;
 
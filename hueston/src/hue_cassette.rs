@@ -0,0 +1,293 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::{HueBridge, HueError};
+
+/// Placeholder for the username in recorded request paths and responses.
+const USERNAME_PLACEHOLDER: &str = "<username>";
+
+/// A single request to the bridge and its response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueInteraction {
+
+    /// HTTP method
+    pub method: String,
+
+    /// Path relative to the bridge URL, with the username replaced
+    pub path: String,
+
+    /// Request body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+
+    /// HTTP status code of the response
+    pub status: u16,
+
+    /// Response body
+    /// Responses that are not JSON are stored as a string
+    pub response: Value,
+}
+
+/// Recorded traffic of a bridge.
+///
+/// A cassette is recorded from a real bridge once and replayed later,
+/// which allows testing deserialization and registration flows without
+/// hardware. Usernames in request paths, registration responses and
+/// whitelists are replaced, other responses are stored as they are, so
+/// review cassettes before sharing them.
+///
+/// Discovery and bridge descriptions are not part of the cassette.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueCassette {
+
+    /// Bridge the traffic was recorded from
+    bridge: HueBridge,
+
+    /// Recorded interactions in order
+    interactions: Vec<HueInteraction>,
+}
+
+impl HueCassette {
+
+    /// Construct an empty `HueCassette` for a bridge.
+    pub fn new(bridge: &HueBridge) -> Self {
+        let username = bridge.get_username().map(|_| USERNAME_PLACEHOLDER.to_string());
+        Self {
+            bridge: bridge.clone().with_username(username),
+            interactions: Vec::new(),
+        }
+    }
+
+    /// Load a cassette from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, HueError> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Save the cassette to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), HueError> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Get the bridge the traffic was recorded from.
+    pub fn get_bridge(&self) -> &HueBridge {
+        &self.bridge
+    }
+
+    /// Get the recorded interactions.
+    pub fn get_interactions(&self) -> &[HueInteraction] {
+        &self.interactions
+    }
+
+    /// Add an interaction.
+    pub fn push(&mut self, interaction: HueInteraction) {
+        self.interactions.push(interaction);
+    }
+}
+
+/// Transport used by a `HueBridgeClient`.
+#[derive(Debug)]
+pub(crate) enum Transport {
+
+    /// Talk to the bridge.
+    Live,
+
+    /// Talk to the bridge and record every exchange.
+    Record {
+        cassette: Mutex<HueCassette>,
+        path: PathBuf,
+    },
+
+    /// Serve recorded responses without network access.
+    Replay {
+        cassette: HueCassette,
+        used: Mutex<Vec<bool>>,
+    },
+}
+
+impl Transport {
+
+    /// Construct a recording transport writing to the given file.
+    pub(crate) fn record(bridge: &HueBridge, path: PathBuf) -> Self {
+        Transport::Record {
+            cassette: Mutex::new(HueCassette::new(bridge)),
+            path,
+        }
+    }
+
+    /// Construct a replaying transport.
+    pub(crate) fn replay(cassette: HueCassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        Transport::Replay {
+            cassette,
+            used: Mutex::new(used),
+        }
+    }
+
+    /// Record an interaction.
+    ///
+    /// Interactions are buffered and written by `flush` or when the
+    /// transport is dropped. Usernames handed out by the bridge and the
+    /// keys of the whitelist are replaced in the response.
+    pub(crate) fn record_interaction(&self, mut interaction: HueInteraction) {
+        if let Transport::Record { cassette, .. } = self {
+            redact_usernames(&mut interaction.response);
+            cassette.lock().unwrap().push(interaction);
+        }
+    }
+
+    /// Save the recorded interactions to the cassette file.
+    pub(crate) fn flush(&self) -> Result<(), HueError> {
+        if let Transport::Record { cassette, path } = self {
+            cassette.lock().unwrap().save(path)?;
+        }
+        Ok(())
+    }
+
+    /// Find the response to a request in the cassette.
+    ///
+    /// Every interaction is served once, in recording order,
+    /// so repeated requests replay the responses as they happened.
+    pub(crate) fn replay_interaction(&self, method: &str, path: &str, body: Option<&Value>) -> Result<(u16, Value), HueError> {
+        let (cassette, used) = match self {
+            Transport::Replay { cassette, used } => (cassette, used),
+            _ => unreachable!("Not a replaying transport"),
+        };
+        let mut used = used.lock().unwrap();
+
+        // Find the first unused interaction matching the request
+        let index = (0..used.len())
+            .find(|&i| {
+                let it = &cassette.interactions[i];
+                !used[i] && it.method == method && it.path == path && it.body.as_ref() == body
+            })
+            .ok_or_else(|| HueError::NotRecorded {
+                method: method.to_string(),
+                path: path.to_string(),
+            })?;
+
+        used[index] = true;
+        let interaction = &cassette.interactions[index];
+        Ok((interaction.status, interaction.response.clone()))
+    }
+}
+
+impl Drop for Transport {
+    fn drop(&mut self) {
+
+        // Errors cannot be reported here, use `flush` to handle them
+        if let Transport::Record { cassette, path } = self {
+            if let Ok(cassette) = cassette.get_mut() {
+                let _ = cassette.save(path);
+            }
+        }
+    }
+}
+
+/// Replace the usernames in a response by a placeholder.
+///
+/// Registration responses look like `[{"success": {"username": "..."}}]`.
+/// The whitelist of the configuration is keyed by username, its keys are
+/// numbered placeholders like `<username 1>` so the entries stay apart.
+fn redact_usernames(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(redact_usernames),
+        Value::Object(map) => {
+            for (key, val) in map.iter_mut() {
+                if key == "username" && val.is_string() {
+                    *val = Value::String(USERNAME_PLACEHOLDER.to_string());
+                } else if key == "whitelist" && val.is_object() {
+                    let entries = std::mem::take(val.as_object_mut().unwrap());
+                    *val = entries
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, (_, entry))| (format!("<username {}>", i + 1), entry))
+                        .collect();
+                } else {
+                    redact_usernames(val);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Get the path of a request URL as stored in a cassette.
+///
+/// The bridge URL is stripped and the username is replaced by a placeholder.
+pub(crate) fn cassette_path(bridge: &HueBridge, url: &str) -> String {
+    let base = bridge.get_url().trim_end_matches('/');
+    let path = url.strip_prefix(base).unwrap_or(url);
+    match bridge.get_username() {
+        Some(username) => path.replacen(&format!("/api/{}", username), &format!("/api/{}", USERNAME_PLACEHOLDER), 1),
+        None => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn interaction(response: Value) -> HueInteraction {
+        HueInteraction {
+            method: "POST".to_string(),
+            path: "/api".to_string(),
+            body: None,
+            status: 200,
+            response,
+        }
+    }
+
+    #[test]
+    fn redacts_registration_usernames() {
+        let mut response = json!([{ "success": { "username": "83b7780291a6ceffbe0bd049104df" } }]);
+        redact_usernames(&mut response);
+        assert_eq!(response, json!([{ "success": { "username": USERNAME_PLACEHOLDER } }]));
+    }
+
+    #[test]
+    fn redacts_whitelist_keys() {
+        let mut response = json!({
+            "name": "Philips hue",
+            "whitelist": {
+                "83b7780291a6ceffbe0bd049104df": { "name": "hueston#laptop" },
+                "ffa6e3c2b4a0e4e3b9f1c0d2d7e21": { "name": "Hue app" }
+            }
+        });
+        redact_usernames(&mut response);
+        assert_eq!(
+            response,
+            json!({
+                "name": "Philips hue",
+                "whitelist": {
+                    "<username 1>": { "name": "hueston#laptop" },
+                    "<username 2>": { "name": "Hue app" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn buffers_until_flush() {
+        let path = std::env::temp_dir().join(format!("hueston-cassette-{}.json", std::process::id()));
+        let bridge = HueBridge::new("http://192.168.1.2:80/", "001788fffe2a3b4c", "Hue", "BSB002", Some("secret"));
+        let transport = Transport::record(&bridge, path.clone());
+        transport.record_interaction(interaction(json!([{ "success": { "username": "secret" } }])));
+        assert!(!path.exists());
+
+        // The buffered interactions are written at once
+        transport.flush().unwrap();
+        assert!(path.exists());
+        drop(transport);
+        let cassette = HueCassette::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cassette.get_interactions().len(), 1);
+        assert_eq!(cassette.get_interactions()[0].response[0]["success"]["username"], USERNAME_PLACEHOLDER);
+    }
+}
@@ -0,0 +1,159 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::HueSwUpdate;
+
+/// Hue Bridge configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueConfig {
+
+    /// Bridge name
+    name: String,
+
+    /// Bridge ID
+    #[serde(rename = "bridgeid")]
+    bridge_id: String,
+
+    /// Hardware model
+    #[serde(rename = "modelid")]
+    model_id: String,
+
+    /// MAC address
+    mac: String,
+
+    /// IP address
+    #[serde(rename = "ipaddress")]
+    ip_address: Option<String>,
+
+    /// API version
+    /// E.g. '1.16.0'
+    #[serde(rename = "apiversion")]
+    api_version: String,
+
+    /// Software version
+    #[serde(rename = "swversion")]
+    sw_version: String,
+
+    /// ZigBee channel
+    #[serde(rename = "zigbeechannel")]
+    zigbee_channel: Option<u8>,
+
+    /// Time zone
+    #[serde(rename = "timezone")]
+    time_zone: Option<String>,
+
+    /// Local time of the bridge
+    #[serde(rename = "localtime")]
+    local_time: Option<String>,
+
+    /// Registered users, by username
+    #[serde(default)]
+    whitelist: BTreeMap<String, HueWhitelistEntry>,
+
+    /// Software update information
+    /// Not available on older firmware
+    #[serde(rename = "swupdate2")]
+    sw_update: Option<HueSwUpdate>,
+}
+
+impl HueConfig {
+
+    /// Get the bridge name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the bridge ID.
+    pub fn get_bridge_id(&self) -> &String {
+        &self.bridge_id
+    }
+
+    /// Get the hardware model.
+    pub fn get_model_id(&self) -> &String {
+        &self.model_id
+    }
+
+    /// Get the MAC address.
+    pub fn get_mac(&self) -> &String {
+        &self.mac
+    }
+
+    /// Get the IP address.
+    pub fn get_ip_address(&self) -> Option<&String> {
+        self.ip_address.as_ref()
+    }
+
+    /// Get the API version.
+    pub fn get_api_version(&self) -> &String {
+        &self.api_version
+    }
+
+    /// Get the software version.
+    pub fn get_sw_version(&self) -> &String {
+        &self.sw_version
+    }
+
+    /// Get the ZigBee channel.
+    pub fn get_zigbee_channel(&self) -> Option<u8> {
+        self.zigbee_channel
+    }
+
+    /// Get the time zone.
+    pub fn get_time_zone(&self) -> Option<&String> {
+        self.time_zone.as_ref()
+    }
+
+    /// Get the local time of the bridge.
+    pub fn get_local_time(&self) -> Option<&String> {
+        self.local_time.as_ref()
+    }
+
+    /// Get the registered users, by username.
+    pub fn get_whitelist(&self) -> &BTreeMap<String, HueWhitelistEntry> {
+        &self.whitelist
+    }
+
+    /// Get the software update information.
+    pub fn get_sw_update(&self) -> Option<&HueSwUpdate> {
+        self.sw_update.as_ref()
+    }
+
+    /// Take the software update information.
+    pub fn into_sw_update(self) -> Option<HueSwUpdate> {
+        self.sw_update
+    }
+}
+
+/// Registered user of a Hue Bridge.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueWhitelistEntry {
+
+    /// Application name
+    name: String,
+
+    /// Date of the last use
+    #[serde(rename = "last use date")]
+    last_use_date: Option<String>,
+
+    /// Date of the registration
+    #[serde(rename = "create date")]
+    create_date: Option<String>,
+}
+
+impl HueWhitelistEntry {
+
+    /// Get the application name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the date of the last use.
+    pub fn get_last_use_date(&self) -> Option<&String> {
+        self.last_use_date.as_ref()
+    }
+
+    /// Get the date of the registration.
+    pub fn get_create_date(&self) -> Option<&String> {
+        self.create_date.as_ref()
+    }
+}
\ No newline at end of file
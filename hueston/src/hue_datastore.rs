@@ -0,0 +1,75 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{
+    HueBridgeClient,
+    HueConfig,
+    HueError,
+    HueGroup,
+    HueLight,
+    HueResourceLink,
+    HueRule,
+    HueScene,
+    HueSchedule,
+    HueSensor,
+};
+
+/// Full datastore as returned by the bridge, by resource ID.
+#[derive(Deserialize, Debug)]
+struct HueDatastoreResponse {
+    lights: BTreeMap<usize, HueLight>,
+    groups: BTreeMap<usize, HueGroup>,
+    config: HueConfig,
+    #[serde(default)]
+    schedules: BTreeMap<usize, HueSchedule>,
+    #[serde(default)]
+    scenes: BTreeMap<String, HueScene>,
+    #[serde(default)]
+    rules: BTreeMap<usize, HueRule>,
+    #[serde(default)]
+    sensors: BTreeMap<usize, HueSensor>,
+    #[serde(default)]
+    resourcelinks: BTreeMap<usize, HueResourceLink>,
+}
+
+/// Consistent snapshot of the full bridge datastore.
+///
+/// Scenes do not contain light states, since the bridge
+/// only reports those when fetching a single scene.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueDatastore {
+    pub lights: Vec<HueLight>,
+    pub groups: Vec<HueGroup>,
+    pub config: HueConfig,
+    pub schedules: Vec<HueSchedule>,
+    pub scenes: Vec<HueScene>,
+    pub rules: Vec<HueRule>,
+    pub sensors: Vec<HueSensor>,
+    pub resourcelinks: Vec<HueResourceLink>,
+}
+
+/// Macro for turning a resource map into a vector of resources with IDs.
+macro_rules! with_ids {
+    ($map:expr) => {
+        $map.into_iter().map(|(k, mut v)| { v.set_id(k); v }).collect()
+    };
+}
+
+impl HueBridgeClient {
+
+    /// Fetch the full datastore in a single request.
+    pub fn fetch_all(&self) -> Result<HueDatastore, HueError> {
+        let ep = self.get_user_endpoint("")?;
+        let resp: HueDatastoreResponse = self.get(ep.trim_end_matches('/'))?;
+        Ok(HueDatastore {
+            lights: with_ids!(resp.lights),
+            groups: with_ids!(resp.groups),
+            config: resp.config,
+            schedules: with_ids!(resp.schedules),
+            scenes: with_ids!(resp.scenes),
+            rules: with_ids!(resp.rules),
+            sensors: with_ids!(resp.sensors),
+            resourcelinks: with_ids!(resp.resourcelinks),
+        })
+    }
+}
\ No newline at end of file
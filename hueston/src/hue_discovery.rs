@@ -0,0 +1,378 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use super::{HueBridge, HueError};
+
+/// SSDP multicast group for IPv4.
+const SSDP_ADDR_V4: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+/// SSDP link-local multicast group for IPv6.
+const SSDP_ADDR_V6: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xc);
+
+/// SSDP port.
+const SSDP_PORT: u16 = 1900;
+
+/// Interval in which the search sockets are polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// IP version used for discovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpVersion {
+
+    /// Search via IPv4 only.
+    V4,
+
+    /// Search via IPv6 only.
+    V6,
+
+    /// Search via IPv4 and IPv6.
+    Any,
+}
+
+/// Hue Bridge discovery options.
+#[derive(Debug, Clone)]
+pub struct DiscoveryOptions {
+
+    /// Maximum number of seconds a bridge may wait before answering.
+    mx: u8,
+
+    /// Upper bound for the whole discovery, including description fetches.
+    timeout: Duration,
+
+    /// Local address to send the search from.
+    interface: Option<IpAddr>,
+
+    /// IP version used for the search.
+    ip_version: IpVersion,
+
+    /// Whether to accept gateways that do not identify as `IpBridge`.
+    compatible: bool,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        Self {
+            mx: 5,
+            timeout: Duration::from_secs(8),
+            interface: None,
+            ip_version: IpVersion::V4,
+            compatible: true,
+        }
+    }
+}
+
+impl DiscoveryOptions {
+
+    /// Construct the default `DiscoveryOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum response delay (MX) in seconds.
+    pub fn with_mx(mut self, mx: u8) -> Self {
+        self.mx = mx.max(1);
+        self
+    }
+
+    /// Set the overall discovery timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send the search from the interface with the given local address.
+    pub fn with_interface(mut self, addr: IpAddr) -> Self {
+        self.interface = Some(addr);
+        self
+    }
+
+    /// Set the IP version used for the search.
+    pub fn with_ip_version(mut self, ip_version: IpVersion) -> Self {
+        self.ip_version = ip_version;
+        self
+    }
+
+    /// Set whether deCONZ, diyHue and other compatible gateways are discovered.
+    ///
+    /// These are recognized by the `hue-bridgeid` header instead
+    /// of the `IpBridge` server string of Philips bridges.
+    pub fn with_compatible_gateways(mut self, compatible: bool) -> Self {
+        self.compatible = compatible;
+        self
+    }
+
+    /// Get the overall discovery timeout.
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Get the local and multicast addresses to search on.
+    fn search_targets(&self) -> Vec<(IpAddr, SocketAddr)> {
+        let mut targets = Vec::new();
+        let want_v4 = self.ip_version != IpVersion::V6;
+        let want_v6 = self.ip_version != IpVersion::V4;
+
+        // Pick the IPv4 target
+        if want_v4 {
+            let target = SocketAddr::new(SSDP_ADDR_V4.into(), SSDP_PORT);
+            match self.interface {
+                Some(addr @ IpAddr::V4(_)) => targets.push((addr, target)),
+                Some(_) => (),
+                None => targets.push((Ipv4Addr::UNSPECIFIED.into(), target)),
+            }
+        }
+
+        // Pick the IPv6 target
+        if want_v6 {
+            let target = SocketAddr::new(SSDP_ADDR_V6.into(), SSDP_PORT);
+            match self.interface {
+                Some(addr @ IpAddr::V6(_)) => targets.push((addr, target)),
+                Some(_) => (),
+                None => targets.push((Ipv6Addr::UNSPECIFIED.into(), target)),
+            }
+        }
+
+        targets
+    }
+}
+
+/// Discovery progress event.
+#[derive(Debug)]
+pub enum DiscoveryEvent<'a> {
+
+    /// A Hue Bridge was found.
+    Found(&'a HueBridge),
+
+    /// A bridge answered the search, but its description could not be fetched.
+    Failed {
+        location: &'a str,
+        error: &'a HueError,
+    },
+}
+
+/// Discover Hue Bridge devices via UPnP.
+pub(crate) fn discover_upnp<F>(options: &DiscoveryOptions, mut on_event: F) -> Result<Vec<HueBridge>, HueError>
+where
+    F: FnMut(DiscoveryEvent),
+{
+    let start = Instant::now();
+    let deadline = start + options.timeout;
+
+    // Bridges answer within MX seconds, allow one more for the network
+    let search_deadline = (start + Duration::from_secs(u64::from(options.mx) + 1)).min(deadline);
+
+    // Bind the sockets and send the search request
+    let mut sockets = Vec::new();
+    let mut last_error = None;
+    for (local, target) in options.search_targets() {
+        match send_search(local, target, options.mx) {
+            Ok(socket) => sockets.push(socket),
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    // Fail if the search could not be sent at all
+    if sockets.is_empty() {
+        return Err(last_error.unwrap_or_else(|| {
+            let msg = "The selected interface does not match the IP version.";
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, msg).into()
+        }));
+    }
+
+    // Create a vector of hue bridges
+    let mut vec = Vec::<HueBridge>::new();
+
+    // Create a set of hue bridge locations
+    let mut found = HashSet::<String>::new();
+
+    // Descriptions are fetched concurrently and reported through the channel
+    let (send, recv) = mpsc::channel::<(String, Result<HueBridge, HueError>)>();
+    let mut pending = Vec::<String>::new();
+    let mut buf = [0u8; 2048];
+
+    loop {
+        let now = Instant::now();
+        let searching = now < search_deadline;
+
+        // Stop once the deadline passed or nothing is left to wait for
+        if now >= deadline || (!searching && pending.is_empty()) {
+            break;
+        }
+
+        // Poll the search sockets for responses
+        if searching {
+            for socket in &sockets {
+                let len = match socket.recv_from(&mut buf) {
+                    Ok((len, _)) => len,
+                    Err(ref err) if is_timeout(err) => continue,
+                    Err(err) => return Err(err.into()),
+                };
+
+                // Continue if the response is not from an unseen bridge
+                let location = match parse_response(&buf[..len], options.compatible) {
+                    Some(location) => location,
+                    None => continue,
+                };
+                if !found.insert(location.clone()) {
+                    continue;
+                }
+
+                // Fetch the bridge description in the background, within the time left
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let send = send.clone();
+                pending.push(location.clone());
+                std::thread::spawn(move || {
+                    let result = reqwest::Client::builder()
+                        .timeout(timeout)
+                        .build()
+                        .map_err(HueError::from)
+                        .and_then(|client| HueBridge::fetch_description(&client, &location));
+                    let _ = send.send((location, result));
+                });
+            }
+        }
+
+        // Collect fetched descriptions
+        let received = if searching {
+            recv.try_recv().ok()
+        } else {
+            recv.recv_timeout(deadline - now).ok()
+        };
+        if let Some((location, result)) = received {
+            pending.retain(|pending| *pending != location);
+            match result {
+                Ok(bridge) => {
+                    on_event(DiscoveryEvent::Found(&bridge));
+                    vec.push(bridge);
+                }
+                Err(error) => on_event(DiscoveryEvent::Failed {
+                    location: &location,
+                    error: &error,
+                }),
+            }
+        }
+    }
+
+    // Report the descriptions that did not arrive in time
+    for location in pending {
+        let msg = "The bridge description did not arrive in time.";
+        let error = std::io::Error::new(std::io::ErrorKind::TimedOut, msg).into();
+        on_event(DiscoveryEvent::Failed {
+            location: &location,
+            error: &error,
+        });
+    }
+
+    Ok(vec)
+}
+
+/// Bind a search socket and send the search request.
+fn send_search(local: IpAddr, target: SocketAddr, mx: u8) -> Result<UdpSocket, HueError> {
+    let socket = UdpSocket::bind(SocketAddr::new(local, 0))?;
+    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+    let req = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {host}\r\nMAN: \"ssdp:discover\"\r\nMX: {mx}\r\nST: ssdp:all\r\n\r\n",
+        host = target,
+        mx = mx,
+    );
+    socket.send_to(req.as_bytes(), target)?;
+    Ok(socket)
+}
+
+/// Test whether a socket error is caused by the read timeout.
+fn is_timeout(err: &std::io::Error) -> bool {
+    matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+}
+
+/// Extract the description location from a Hue Bridge search response.
+///
+/// Responses from other UPnP devices yield `None`.
+/// Compatible gateways are accepted if `compatible` is set.
+fn parse_response(data: &[u8], compatible: bool) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.split("\r\n");
+
+    // Make sure this is a successful response
+    if !lines.next()?.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+
+    // Extract the server, bridge ID and location headers
+    let mut server = None;
+    let mut bridge_id = None;
+    let mut location = None;
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("server") {
+            server = Some(value);
+        } else if name.eq_ignore_ascii_case("hue-bridgeid") {
+            bridge_id = Some(value);
+        } else if name.eq_ignore_ascii_case("location") {
+            location = Some(value);
+        }
+    }
+
+    // Test whether the server contains "IpBridge" or a compatible gateway answered
+    let is_bridge = server.is_some_and(|server| server.contains("IpBridge"));
+    if !(is_bridge || compatible && bridge_id.is_some()) {
+        return None;
+    }
+
+    location.map(ToString::to_string)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHILIPS_RESPONSE: &str = "HTTP/1.1 200 OK\r\n\
+        CACHE-CONTROL: max-age=100\r\n\
+        EXT:\r\n\
+        LOCATION: http://192.168.1.2:80/description.xml\r\n\
+        SERVER: Linux/3.14.0 UPnP/1.0 IpBridge/1.26.0\r\n\
+        hue-bridgeid: 001788FFFE2A3B4C\r\n\
+        ST: upnp:rootdevice\r\n\r\n";
+
+    const DECONZ_RESPONSE: &str = "HTTP/1.1 200 OK\r\n\
+        location: http://192.168.1.3:80/description.xml\r\n\
+        server: FreeRTOS/7.4.2, UPnP/1.0, IpBridge/1.16.0\r\n\
+        Hue-BridgeID: 00212EFFFF01A2B3\r\n\r\n";
+
+    const DIYHUE_RESPONSE: &str = "HTTP/1.1 200 OK\r\n\
+        LOCATION: http://192.168.1.4:80/description.xml\r\n\
+        SERVER: Linux/3.14.0 UPnP/1.0 diyHue/1.0\r\n\
+        hue-bridgeid: B827EBFFFE1A2B3C\r\n\r\n";
+
+    #[test]
+    fn parses_bridge_responses() {
+        let location = parse_response(PHILIPS_RESPONSE.as_bytes(), false);
+        assert_eq!(location.as_deref(), Some("http://192.168.1.2:80/description.xml"));
+
+        // Header names are case-insensitive
+        let location = parse_response(DECONZ_RESPONSE.as_bytes(), false);
+        assert_eq!(location.as_deref(), Some("http://192.168.1.3:80/description.xml"));
+    }
+
+    #[test]
+    fn accepts_compatible_gateways_if_enabled() {
+        let location = parse_response(DIYHUE_RESPONSE.as_bytes(), true);
+        assert_eq!(location.as_deref(), Some("http://192.168.1.4:80/description.xml"));
+        assert_eq!(parse_response(DIYHUE_RESPONSE.as_bytes(), false), None);
+    }
+
+    #[test]
+    fn ignores_other_responses() {
+        let router = "HTTP/1.1 200 OK\r\nLOCATION: http://192.168.1.1:1900/igd.xml\r\nSERVER: Linux UPnP/1.0 MiniUPnPd/2.1\r\n\r\n";
+        assert_eq!(parse_response(router.as_bytes(), true), None);
+
+        // Failed responses, responses without a location and garbage are ignored
+        let failed = PHILIPS_RESPONSE.replacen("200 OK", "404 Not Found", 1);
+        assert_eq!(parse_response(failed.as_bytes(), true), None);
+        let unlocated = "HTTP/1.1 200 OK\r\nSERVER: Linux/3.14.0 UPnP/1.0 IpBridge/1.26.0\r\n\r\n";
+        assert_eq!(parse_response(unlocated.as_bytes(), true), None);
+        assert_eq!(parse_response(&[0xff, 0xfe, 0x00], true), None);
+    }
+}
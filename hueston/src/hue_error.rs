@@ -0,0 +1,137 @@
+use std::fmt;
+
+use super::HueErrorCode;
+use super::hue_features::{HueApiVersion, HueResourceKind};
+
+/// Hueston error.
+#[derive(Debug)]
+pub enum HueError {
+
+    /// A socket operation failed.
+    Io(std::io::Error),
+
+    /// An HTTP request failed.
+    Http(reqwest::Error),
+
+    /// The bridge answered with an unsuccessful HTTP status code.
+    Status(u16),
+
+    /// The bridge reported an error.
+    Bridge {
+        code: i32,
+        address: String,
+        description: String,
+    },
+
+    /// The client has no username, the bridge needs to be registered first.
+    NotRegistered,
+
+    /// A JSON response could not be deserialized.
+    Json(serde_json::Error),
+
+    /// A bridge description could not be deserialized.
+    Xml(String),
+
+    /// A different bridge answered at the bridge URL.
+    BridgeMismatch {
+        expected: String,
+        found: String,
+    },
+
+    /// The bridge with the given ID could not be discovered.
+    BridgeNotFound(String),
+
+    /// No resource with the given unique ID is known.
+    UnknownResource(String),
+
+    /// The bridge answered with an unexpected response.
+    UnexpectedResponse(serde_json::Value),
+
+    /// The archive version is not supported.
+    UnsupportedArchive(u32),
+
+    /// The bridge firmware does not support a feature.
+    Unsupported {
+        feature: String,
+        required: HueApiVersion,
+    },
+
+    /// The bridge has no space left for resources of a kind.
+    CapacityExceeded(HueResourceKind),
+
+    /// The replayed cassette has no response for a request.
+    NotRecorded {
+        method: String,
+        path: String,
+    },
+}
+
+impl fmt::Display for HueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HueError::Io(err) => write!(f, "I/O error: {}", err),
+            HueError::Http(err) => write!(f, "HTTP error: {}", err),
+            HueError::Status(code) => write!(f, "Unexpected HTTP status {}", code),
+            HueError::Bridge { code, address, description } => {
+                write!(f, "Bridge error {} at '{}': {}", code, address, description)
+            }
+            HueError::NotRegistered => write!(f, "The bridge is not registered"),
+            HueError::Json(err) => write!(f, "Invalid JSON response: {}", err),
+            HueError::Xml(err) => write!(f, "Invalid bridge description: {}", err),
+            HueError::BridgeMismatch { expected, found } => write!(
+                f,
+                "Expected bridge {}, but bridge {} answered",
+                expected, found
+            ),
+            HueError::BridgeNotFound(id) => write!(f, "Unable to find bridge {}", id),
+            HueError::UnknownResource(uid) => write!(f, "Unknown resource {}", uid),
+            HueError::UnexpectedResponse(value) => write!(f, "Unexpected response: {}", value),
+            HueError::UnsupportedArchive(version) => {
+                write!(f, "Unsupported archive version {}", version)
+            }
+            HueError::Unsupported { feature, required } => {
+                write!(f, "'{}' requires API version {}", feature, required)
+            }
+            HueError::CapacityExceeded(kind) => write!(f, "The bridge has no space left for {}", kind),
+            HueError::NotRecorded { method, path } => {
+                write!(f, "No recorded response for {} {}", method, path)
+            }
+        }
+    }
+}
+
+impl HueError {
+
+    /// Get the bridge error code, if the bridge reported an error.
+    pub fn error_code(&self) -> Option<HueErrorCode> {
+        match self {
+            HueError::Bridge { code, .. } => Some(HueErrorCode::from_type(*code)),
+            _ => None,
+        }
+    }
+
+    /// Test whether the request did not reach the bridge or its response got lost.
+    pub fn is_transport(&self) -> bool {
+        matches!(self, HueError::Http(_) | HueError::Io(_))
+    }
+}
+
+impl std::error::Error for HueError {}
+
+impl From<std::io::Error> for HueError {
+    fn from(err: std::io::Error) -> Self {
+        HueError::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for HueError {
+    fn from(err: reqwest::Error) -> Self {
+        HueError::Http(err)
+    }
+}
+
+impl From<serde_json::Error> for HueError {
+    fn from(err: serde_json::Error) -> Self {
+        HueError::Json(err)
+    }
+}
\ No newline at end of file
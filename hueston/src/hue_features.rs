@@ -0,0 +1,225 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+use super::{HueBridgeClient, HueError};
+
+/// Hue API version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HueApiVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl HueApiVersion {
+
+    /// Construct a new `HueApiVersion`.
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parse a version like `1.16.0`.
+    ///
+    /// Missing components are treated as zero.
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().split('.').map(str::parse::<u16>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for HueApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl Serialize for HueApiVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for HueApiVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        Self::parse(&version).ok_or_else(|| serde::de::Error::custom(format!("invalid API version '{}'", version)))
+    }
+}
+
+/// First API version supporting `bri_inc`, `sat_inc`, `hue_inc` and `ct_inc`.
+pub const API_INCREMENTS: HueApiVersion = HueApiVersion::new(1, 7, 0);
+
+/// First API version supporting resource links.
+pub const API_RESOURCELINKS: HueApiVersion = HueApiVersion::new(1, 11, 0);
+
+/// First API version supporting the capabilities endpoint.
+pub const API_CAPABILITIES: HueApiVersion = HueApiVersion::new(1, 15, 0);
+
+/// First API version supporting entertainment streaming.
+pub const API_ENTERTAINMENT: HueApiVersion = HueApiVersion::new(1, 22, 0);
+
+/// Kind of a bridge resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HueResourceKind {
+    Lights,
+    Sensors,
+    Groups,
+    Scenes,
+    Rules,
+    Schedules,
+    ResourceLinks,
+}
+
+impl fmt::Display for HueResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HueResourceKind::Lights => "lights",
+            HueResourceKind::Sensors => "sensors",
+            HueResourceKind::Groups => "groups",
+            HueResourceKind::Scenes => "scenes",
+            HueResourceKind::Rules => "rules",
+            HueResourceKind::Schedules => "schedules",
+            HueResourceKind::ResourceLinks => "resourcelinks",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Available and total number of resources of a kind.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HueCapacity {
+
+    /// Number of resources that can still be created
+    pub available: u32,
+
+    /// Maximum number of resources
+    pub total: u32,
+}
+
+/// Time zones supported by the bridge.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct HueTimezones {
+    pub values: Vec<String>,
+}
+
+/// Bridge capabilities as reported by `/capabilities`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueCapabilities {
+    pub lights: HueCapacity,
+    pub sensors: HueCapacity,
+    pub groups: HueCapacity,
+    pub scenes: HueCapacity,
+    pub rules: HueCapacity,
+    pub schedules: HueCapacity,
+    pub resourcelinks: HueCapacity,
+    #[serde(default)]
+    pub timezones: HueTimezones,
+}
+
+impl HueCapabilities {
+
+    /// Get the capacity for a resource kind.
+    pub fn capacity(&self, kind: HueResourceKind) -> HueCapacity {
+        match kind {
+            HueResourceKind::Lights => self.lights,
+            HueResourceKind::Sensors => self.sensors,
+            HueResourceKind::Groups => self.groups,
+            HueResourceKind::Scenes => self.scenes,
+            HueResourceKind::Rules => self.rules,
+            HueResourceKind::Schedules => self.schedules,
+            HueResourceKind::ResourceLinks => self.resourcelinks,
+        }
+    }
+}
+
+/// Features supported by a bridge.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BridgeFeatures {
+
+    /// API version of the bridge
+    api_version: HueApiVersion,
+
+    /// Capabilities, if the bridge reports them
+    capabilities: Option<HueCapabilities>,
+}
+
+impl BridgeFeatures {
+
+    /// Construct a new `BridgeFeatures`.
+    pub fn new(api_version: HueApiVersion, capabilities: Option<HueCapabilities>) -> Self {
+        Self { api_version, capabilities }
+    }
+
+    /// Get the API version.
+    pub fn get_api_version(&self) -> HueApiVersion {
+        self.api_version
+    }
+
+    /// Get the capabilities.
+    pub fn get_capabilities(&self) -> Option<&HueCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// Test whether the API version is at least the given version.
+    pub fn at_least(&self, version: HueApiVersion) -> bool {
+        self.api_version >= version
+    }
+
+    /// Test whether relative changes like `bri_inc` are supported.
+    pub fn supports_increments(&self) -> bool {
+        self.at_least(API_INCREMENTS)
+    }
+
+    /// Test whether resource links are supported.
+    pub fn supports_resourcelinks(&self) -> bool {
+        self.at_least(API_RESOURCELINKS)
+    }
+
+    /// Test whether entertainment streaming is supported.
+    pub fn supports_entertainment(&self) -> bool {
+        self.at_least(API_ENTERTAINMENT)
+    }
+
+    /// Get the number of resources of a kind that can still be created.
+    ///
+    /// Returns `None` if the bridge does not report its capabilities.
+    pub fn available(&self, kind: HueResourceKind) -> Option<u32> {
+        self.capabilities.as_ref().map(|caps| caps.capacity(kind).available)
+    }
+
+    /// Test whether the given number of resources can be created.
+    ///
+    /// Bridges without capabilities are assumed to have enough space.
+    pub fn fits(&self, kind: HueResourceKind, count: u32) -> bool {
+        self.available(kind).is_none_or(|available| count <= available)
+    }
+}
+
+impl HueBridgeClient {
+
+    /// Fetch the bridge capabilities.
+    pub fn fetch_capabilities(&self) -> Result<HueCapabilities, HueError> {
+        self.get(&self.get_user_endpoint("/capabilities")?)
+    }
+
+    /// Fetch the features supported by the bridge.
+    pub fn fetch_features(&self) -> Result<BridgeFeatures, HueError> {
+        let config = self.fetch_config()?;
+        let api_version = HueApiVersion::parse(config.get_api_version())
+            .ok_or_else(|| HueError::UnexpectedResponse(config.get_api_version().as_str().into()))?;
+
+        // Older bridges do not know the capabilities endpoint
+        let capabilities = if api_version >= API_CAPABILITIES {
+            Some(self.fetch_capabilities()?)
+        } else {
+            None
+        };
+
+        Ok(BridgeFeatures::new(api_version, capabilities))
+    }
+}
\ No newline at end of file
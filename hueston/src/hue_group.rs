@@ -0,0 +1,58 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Hue Group.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueGroup {
+
+    /// Group ID on the bridge
+    #[serde(default)]
+    id: usize,
+
+    /// Group name
+    name: String,
+
+    /// IDs of the lights in the group
+    lights: Vec<String>,
+
+    /// Group type
+    /// E.g. 'LightGroup', 'Room' or 'Zone'
+    #[serde(rename = "type")]
+    r#type: String,
+
+    /// Room class
+    /// Only available for rooms and zones
+    class: Option<String>,
+}
+
+impl HueGroup {
+
+    /// Get the group ID on the bridge.
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the group name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the IDs of the lights in the group.
+    pub fn get_light_ids(&self) -> &[String] {
+        &self.lights
+    }
+
+    /// Get the group type.
+    pub fn get_type(&self) -> &String {
+        &self.r#type
+    }
+
+    /// Get the room class.
+    pub fn get_class(&self) -> Option<&String> {
+        self.class.as_ref()
+    }
+
+    /// Set the group ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{HueBridgeClient, HueError, HueGroup, HueLight, HueSensor};
+
+/// A resource together with the index of the bridge it belongs to.
+#[derive(Debug)]
+struct HomeEntry<T> {
+    bridge: usize,
+    resource: T,
+}
+
+/// Hue Home.
+///
+/// Combines several bridges into one home. Lights, groups and sensors
+/// of all bridges are addressed by a unique ID, and commands are routed
+/// to the bridge the resource belongs to.
+///
+/// Lights and physical sensors use the unique ID reported by the bridge.
+/// Groups and virtual sensors are addressed as `<bridge id>/groups/<id>`
+/// and `<bridge id>/sensors/<id>`.
+#[derive(Debug)]
+pub struct HueHome {
+    clients: Vec<HueBridgeClient>,
+    lights: BTreeMap<String, HomeEntry<HueLight>>,
+    groups: BTreeMap<String, HomeEntry<HueGroup>>,
+    sensors: BTreeMap<String, HomeEntry<HueSensor>>,
+}
+
+impl HueHome {
+
+    /// Construct a new `HueHome` from bridge clients.
+    ///
+    /// The home is empty until `HueHome::refresh` is called.
+    pub fn new(clients: Vec<HueBridgeClient>) -> Self {
+        Self {
+            clients,
+            lights: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            sensors: BTreeMap::new(),
+        }
+    }
+
+    /// Get the bridge clients.
+    pub fn clients(&self) -> &[HueBridgeClient] {
+        &self.clients
+    }
+
+    /// Fetch lights, groups and sensors from all bridges.
+    ///
+    /// A bridge that fails does not affect the others. Its resources
+    /// from the previous refresh are kept, and the error is returned
+    /// together with the bridge ID.
+    pub fn refresh(&mut self) -> Vec<(String, HueError)> {
+        let mut errors = Vec::new();
+        for bridge in 0..self.clients.len() {
+            if let Err(err) = self.refresh_bridge(bridge) {
+                errors.push((self.clients[bridge].get_bridge_id(), err));
+            }
+        }
+        errors
+    }
+
+    /// Fetch lights, groups and sensors from a single bridge.
+    ///
+    /// The resources are only replaced if all of them could be fetched.
+    fn refresh_bridge(&mut self, bridge: usize) -> Result<(), HueError> {
+        let client = &self.clients[bridge];
+        let bridge_id = client.get_bridge_id();
+        let lights = client.fetch_lights()?;
+        let groups = client.fetch_groups()?;
+        let sensors = client.fetch_sensors()?;
+
+        // Replace the lights
+        self.lights.retain(|_, entry| entry.bridge != bridge);
+        for resource in lights {
+            let uid = resource.get_unique_id().clone();
+            self.lights.insert(uid, HomeEntry { bridge, resource });
+        }
+
+        // Replace the groups
+        self.groups.retain(|_, entry| entry.bridge != bridge);
+        for resource in groups {
+            let uid = format!("{}/groups/{}", bridge_id, resource.get_id());
+            self.groups.insert(uid, HomeEntry { bridge, resource });
+        }
+
+        // Replace the sensors
+        self.sensors.retain(|_, entry| entry.bridge != bridge);
+        for resource in sensors {
+            let uid = match resource.get_unique_id() {
+                Some(uid) => uid.clone(),
+                None => format!("{}/sensors/{}", bridge_id, resource.get_id()),
+            };
+            self.sensors.insert(uid, HomeEntry { bridge, resource });
+        }
+        Ok(())
+    }
+
+    /// Iterate over all lights.
+    pub fn lights(&self) -> impl Iterator<Item = (&String, &HueLight)> {
+        self.lights.iter().map(|(uid, entry)| (uid, &entry.resource))
+    }
+
+    /// Iterate over all groups.
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &HueGroup)> {
+        self.groups.iter().map(|(uid, entry)| (uid, &entry.resource))
+    }
+
+    /// Iterate over all sensors.
+    pub fn sensors(&self) -> impl Iterator<Item = (&String, &HueSensor)> {
+        self.sensors.iter().map(|(uid, entry)| (uid, &entry.resource))
+    }
+
+    /// Get a light by its unique ID.
+    pub fn light(&self, uid: &str) -> Option<&HueLight> {
+        self.lights.get(uid).map(|entry| &entry.resource)
+    }
+
+    /// Get a group by its unique ID.
+    pub fn group(&self, uid: &str) -> Option<&HueGroup> {
+        self.groups.get(uid).map(|entry| &entry.resource)
+    }
+
+    /// Get a sensor by its unique ID.
+    pub fn sensor(&self, uid: &str) -> Option<&HueSensor> {
+        self.sensors.get(uid).map(|entry| &entry.resource)
+    }
+
+    /// Get the unique IDs of the lights in a group.
+    pub fn group_lights(&self, uid: &str) -> Vec<&String> {
+        let entry = match self.groups.get(uid) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+        let ids = entry.resource.get_light_ids();
+        self.lights
+            .iter()
+            .filter(|(_, light)| light.bridge == entry.bridge)
+            .filter(|(_, light)| ids.contains(&light.resource.get_id().to_string()))
+            .map(|(uid, _)| uid)
+            .collect()
+    }
+
+    /// Get the client of the bridge a light belongs to.
+    pub fn light_client(&self, uid: &str) -> Option<&HueBridgeClient> {
+        self.lights.get(uid).map(|entry| &self.clients[entry.bridge])
+    }
+
+    /// Set the state of a light.
+    pub fn set_light_state(&self, uid: &str, state: &HashMap<&'static str, serde_json::Value>) -> Result<(), HueError> {
+        let entry = self
+            .lights
+            .get(uid)
+            .ok_or_else(|| HueError::UnknownResource(uid.to_string()))?;
+        self.clients[entry.bridge].set_light_state(entry.resource.get_id(), state)
+    }
+
+    /// Set the state of a light without retrying.
+    ///
+    /// See `HueBridgeClient::set_light_state_once`.
+    pub fn set_light_state_once(&self, uid: &str, state: &HashMap<&'static str, serde_json::Value>) -> Result<(), HueError> {
+        let entry = self
+            .lights
+            .get(uid)
+            .ok_or_else(|| HueError::UnknownResource(uid.to_string()))?;
+        self.clients[entry.bridge].set_light_state_once(entry.resource.get_id(), state)
+    }
+
+    /// Set the state of all lights in a group.
+    pub fn set_group_action(&self, uid: &str, action: &HashMap<&'static str, serde_json::Value>) -> Result<(), HueError> {
+        let entry = self
+            .groups
+            .get(uid)
+            .ok_or_else(|| HueError::UnknownResource(uid.to_string()))?;
+        self.clients[entry.bridge].set_group_action(entry.resource.get_id(), action)
+    }
+}
\ No newline at end of file
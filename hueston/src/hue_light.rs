@@ -1,10 +1,21 @@
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::HueDeviceUpdate;
+
+/// Largest difference of color coordinates considered equal.
+///
+/// The bridge reports coordinates with four decimals.
+const XY_EPSILON: f32 = 0.0005;
+
 /// Hue Bridge Device.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct HueLight {
 
+    /// Light ID on the bridge
+    #[serde(default)]
+    id: usize,
+
     /// State
     state: HueLightState,
 
@@ -20,11 +31,11 @@ pub struct HueLight {
     model_id: String,
 
     /// Manufacturer name
-    #[serde(rename = "manufacturername")]
+    #[serde(rename = "manufacturername", default, deserialize_with = "crate::hue_resp::null_default")]
     manufacturer_name: String,
 
     /// Product name
-    #[serde(rename = "productname")]
+    #[serde(rename = "productname", default, deserialize_with = "crate::hue_resp::null_default")]
     product_name: String,
 
     /// Unique ID
@@ -32,8 +43,12 @@ pub struct HueLight {
     unique_id: String,
 
     /// Software version
-    #[serde(rename = "swversion")]
+    #[serde(rename = "swversion", default, deserialize_with = "crate::hue_resp::null_default")]
     sw_version: String,
+
+    /// Software update information
+    #[serde(rename = "swupdate")]
+    sw_update: Option<HueDeviceUpdate>,
 }
 
 impl HueLight {
@@ -41,6 +56,63 @@ impl HueLight {
     pub fn get_name(&self) -> &String {
         &self.name
     }
+
+    /// Get the light ID on the bridge.
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the unique ID.
+    pub fn get_unique_id(&self) -> &String {
+        &self.unique_id
+    }
+
+    /// Get the light state.
+    pub fn get_state(&self) -> &HueLightState {
+        &self.state
+    }
+
+    /// Get the light type.
+    pub fn get_type(&self) -> &String {
+        &self.r#type
+    }
+
+    /// Get the hardware model.
+    pub fn get_model_id(&self) -> &String {
+        &self.model_id
+    }
+
+    /// Get the manufacturer name.
+    pub fn get_manufacturer_name(&self) -> &String {
+        &self.manufacturer_name
+    }
+
+    /// Get the product name.
+    pub fn get_product_name(&self) -> &String {
+        &self.product_name
+    }
+
+    /// Get the software version.
+    pub fn get_sw_version(&self) -> &String {
+        &self.sw_version
+    }
+
+    /// Get the software update information.
+    pub fn get_sw_update(&self) -> Option<&HueDeviceUpdate> {
+        self.sw_update.as_ref()
+    }
+
+    /// Compute the smallest update from the light state to the target state.
+    ///
+    /// See `HueLightState::diff`, the batch addresses this light.
+    pub fn diff(&self, target: &HueLightState) -> HueLightBatch {
+        self.state.diff(self.id, target)
+    }
+
+    /// Set the light ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
 }
 
 impl std::ops::Deref for HueLight {
@@ -51,7 +123,11 @@ impl std::ops::Deref for HueLight {
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Light state.
+///
+/// Attributes a light does not support, like the color of a white bulb
+/// or the brightness of a plug, are reported as zero.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct HueLightState {
 
     /// State of the light
@@ -59,28 +135,35 @@ pub struct HueLightState {
 
     /// Brightness
     /// From 1 to 254
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     bri: u8,
 
     /// Hue
     /// From 0 to 65535
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     hue: u16,
 
     /// Saturation
     /// From 0 to 254
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     sat: u8,
 
     /// Dynamic effect
     /// Either 'none' or 'colorloop'
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     effect: String,
 
     /// Color coordinates in CIE color space
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     xy: HueLightCoords,
 
     /// Color temperature
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     ct: u16,
 
     /// Alert effect
     /// Either 'none', 'select' or 'lselect'
+    #[serde(default, deserialize_with = "crate::hue_resp::null_default")]
     alert: String,
 
     /// Color mode
@@ -95,14 +178,207 @@ impl HueLightState {
     pub fn is_on(&self) -> bool {
         self.on
     }
+
+    /// Get the brightness.
+    pub fn get_brightness(&self) -> u8 {
+        self.bri
+    }
+
+    /// Get the hue.
+    pub fn get_hue(&self) -> u16 {
+        self.hue
+    }
+
+    /// Get the saturation.
+    pub fn get_saturation(&self) -> u8 {
+        self.sat
+    }
+
+    /// Get the dynamic effect.
+    pub fn get_effect(&self) -> &String {
+        &self.effect
+    }
+
+    /// Get the color coordinates in CIE color space.
+    pub fn get_xy(&self) -> [f32; 2] {
+        self.xy.into()
+    }
+
+    /// Get the color coordinates in CIE color space.
+    pub fn get_coords(&self) -> HueLightCoords {
+        self.xy
+    }
+
+    /// Get the color temperature.
+    pub fn get_temperature(&self) -> u16 {
+        self.ct
+    }
+
+    /// Get the alert effect.
+    pub fn get_alert(&self) -> &String {
+        &self.alert
+    }
+
+    /// Get the color mode.
+    pub fn get_color_mode(&self) -> Option<&String> {
+        self.colormode.as_ref()
+    }
+
+    /// Test whether the light is reachable.
+    pub fn is_reachable(&self) -> bool {
+        self.reachable
+    }
+
+    /// Compute the smallest update from this state to the target state.
+    ///
+    /// Only attributes that actually change are included. The color is
+    /// compared in the color mode of the target, so hue and saturation are
+    /// skipped if the target is in xy mode and its coordinates match.
+    /// If the color mode changes, the color of the target is always
+    /// included, since the light shows the color of its current mode.
+    /// Attributes of a light that is switched off are not included.
+    ///
+    /// The batch addresses the light with the given ID.
+    pub fn diff(&self, light_index: usize, target: &HueLightState) -> HueLightBatch {
+        let mut batch = HueLightBatch::new(light_index);
+        if self.on != target.on {
+            batch.on(target.on);
+        }
+
+        // Attributes cannot be changed while the light is off
+        if !target.on {
+            return batch;
+        }
+        if self.bri != target.bri {
+            batch.brightness(target.bri);
+        }
+
+        // Compare the color in the color mode of the target
+        let mode_changed = self.colormode != target.colormode;
+        let xy_changed =
+            (self.xy.x - target.xy.x).abs() > XY_EPSILON || (self.xy.y - target.xy.y).abs() > XY_EPSILON;
+        match target.colormode.as_deref() {
+            Some("xy") if mode_changed || xy_changed => {
+                batch.xy(target.get_xy());
+            }
+            Some("ct") if mode_changed || self.ct != target.ct => {
+                batch.temperature(target.ct);
+            }
+            Some("hs") => {
+                if mode_changed || self.hue != target.hue {
+                    batch.hue(target.hue);
+                }
+                if mode_changed || self.sat != target.sat {
+                    batch.saturation(target.sat);
+                }
+            }
+            _ => (),
+        }
+        batch
+    }
+
+    /// Apply the attributes of a command batch to the state.
+    ///
+    /// The color mode follows the last color attribute of the batch,
+    /// relative changes are clamped to the valid ranges.
+    pub fn apply(&mut self, batch: &HueLightBatch) {
+        let get = |key: &str| batch.map.get(key);
+        let get_i64 = |key: &str| get(key).and_then(serde_json::Value::as_i64);
+
+        // Apply absolute values
+        if let Some(on) = get("on").and_then(serde_json::Value::as_bool) {
+            self.on = on;
+        }
+        if let Some(bri) = get_i64("bri") {
+            self.bri = bri.clamp(0, 254) as u8;
+        }
+        if let Some(hue) = get_i64("hue") {
+            self.hue = hue.clamp(0, 65535) as u16;
+            self.colormode = Some("hs".to_string());
+        }
+        if let Some(sat) = get_i64("sat") {
+            self.sat = sat.clamp(0, 254) as u8;
+            self.colormode = Some("hs".to_string());
+        }
+        if let Some(xy) = get("xy").and_then(|xy| serde_json::from_value::<[f32; 2]>(xy.clone()).ok()) {
+            self.xy = xy.into();
+            self.colormode = Some("xy".to_string());
+        }
+        if let Some(ct) = get_i64("ct") {
+            self.ct = ct.clamp(0, 65535) as u16;
+            self.colormode = Some("ct".to_string());
+        }
+
+        // Apply relative changes, the hue wraps around by truncation
+        if let Some(inc) = get_i64("bri_inc") {
+            self.bri = (i64::from(self.bri) + inc).clamp(1, 254) as u8;
+        }
+        if let Some(inc) = get_i64("sat_inc") {
+            self.sat = (i64::from(self.sat) + inc).clamp(0, 254) as u8;
+            self.colormode = Some("hs".to_string());
+        }
+        if let Some(inc) = get_i64("hue_inc") {
+            self.hue = (i64::from(self.hue) + inc) as u16;
+            self.colormode = Some("hs".to_string());
+        }
+        if let Some(inc) = get_i64("ct_inc") {
+            self.ct = (i64::from(self.ct) + inc).clamp(153, 500) as u16;
+            self.colormode = Some("ct".to_string());
+        }
+    }
+
+    /// Reduce a command batch to the attributes that change this state.
+    ///
+    /// The transition time is kept if anything changes.
+    pub fn minimize(&self, batch: &HueLightBatch) -> HueLightBatch {
+        let mut target = self.clone();
+        target.apply(batch);
+        let mut changes = self.diff(batch.index, &target);
+        if !changes.is_empty() {
+            if let Some(tt) = batch.map.get("transitiontime") {
+                changes.map.insert("transitiontime", tt.clone());
+            }
+        }
+        changes
+    }
 }
 
-#[derive(Deserialize, Debug)]
+/// Color coordinates in CIE color space.
+///
+/// The bridge represents them as an `[x, y]` array.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(from = "[f32; 2]", into = "[f32; 2]")]
 pub struct HueLightCoords {
     x: f32,
     y: f32,
 }
 
+impl HueLightCoords {
+
+    /// Get the x coordinate.
+    pub fn get_x(&self) -> f32 {
+        self.x
+    }
+
+    /// Get the y coordinate.
+    pub fn get_y(&self) -> f32 {
+        self.y
+    }
+}
+
+impl From<[f32; 2]> for HueLightCoords {
+    fn from(xy: [f32; 2]) -> Self {
+        Self { x: xy[0], y: xy[1] }
+    }
+}
+
+impl From<HueLightCoords> for [f32; 2] {
+    fn from(coords: HueLightCoords) -> Self {
+        [coords.x, coords.y]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct HueLightBatch {
     index: usize,
     map: HashMap<&'static str, serde_json::Value>
@@ -129,6 +405,26 @@ impl HueLightBatch {
         }
     }
 
+    /// Get the light index.
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Test whether the batch contains no attributes besides the transition time.
+    pub fn is_empty(&self) -> bool {
+        self.map.keys().all(|key| *key == "transitiontime")
+    }
+
+    /// Add the attributes of another batch.
+    ///
+    /// Attributes set in both batches take the value of the other batch.
+    pub fn merge(&mut self, other: &HueLightBatch) -> &mut Self {
+        for (key, val) in &other.map {
+            self.map.insert(key, val.clone());
+        }
+        self
+    }
+
     /// Get the light index and parameter HashMap.
     pub fn build(self) -> (usize, HashMap<&'static str, serde_json::Value>) {
         (self.index, self.map)
@@ -139,5 +435,71 @@ impl HueLightBatch {
     impl_batch_op!(brightness => bri: u8);
     impl_batch_op!(saturation => sat: u8);
     impl_batch_op!(hue: u16);
+    impl_batch_op!(xy: [f32; 2]);
     impl_batch_op!(transition_time => transitiontime: u16);
-}
\ No newline at end of file
+    impl_batch_op!(brightness_increment => bri_inc: i16);
+    impl_batch_op!(saturation_increment => sat_inc: i16);
+    impl_batch_op!(hue_increment => hue_inc: i32);
+    impl_batch_op!(temperature_increment => ct_inc: i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Build a light state that is on in the given color mode.
+    fn state(colormode: &str, xy: [f32; 2], ct: u16, hue: u16, sat: u8) -> HueLightState {
+        serde_json::from_value(json!({
+            "on": true,
+            "bri": 144,
+            "hue": hue,
+            "sat": sat,
+            "xy": xy,
+            "ct": ct,
+            "colormode": colormode,
+            "reachable": true
+        }))
+        .unwrap()
+    }
+
+    /// Get the names of the attributes in a batch, sorted.
+    fn keys(batch: HueLightBatch) -> Vec<&'static str> {
+        let mut keys: Vec<_> = batch.build().1.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn diff_skips_unchanged_attributes() {
+        let current = state("xy", [0.5, 0.4], 366, 8000, 200);
+        assert!(keys(current.diff(1, &current)).is_empty());
+
+        // Attributes of other color modes do not matter
+        let target = state("xy", [0.5, 0.4], 153, 0, 0);
+        assert!(keys(current.diff(1, &target)).is_empty());
+        let target = state("xy", [0.3, 0.3], 366, 8000, 200);
+        assert_eq!(keys(current.diff(1, &target)), vec!["xy"]);
+    }
+
+    #[test]
+    fn diff_sends_the_color_if_the_mode_changes() {
+        let current = state("xy", [0.5, 0.4], 366, 8000, 200);
+
+        // The values match, but the light still shows its xy color
+        let target = state("ct", [0.5, 0.4], 366, 8000, 200);
+        assert_eq!(keys(current.diff(1, &target)), vec!["ct"]);
+        let target = state("hs", [0.5, 0.4], 366, 8000, 200);
+        assert_eq!(keys(current.diff(1, &target)), vec!["hue", "sat"]);
+        let target = state("xy", [0.5, 0.4], 366, 8000, 200);
+        assert_eq!(keys(state("ct", [0.5, 0.4], 366, 8000, 200).diff(1, &target)), vec!["xy"]);
+    }
+
+    #[test]
+    fn diff_leaves_out_attributes_of_lights_switched_off() {
+        let current = state("xy", [0.5, 0.4], 366, 8000, 200);
+        let mut target = state("ct", [0.3, 0.3], 153, 0, 0);
+        target.on = false;
+        assert_eq!(keys(current.diff(1, &target)), vec!["on"]);
+    }
+}
@@ -0,0 +1,91 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// Kind of gateway implementing the Hue API.
+///
+/// deCONZ/Phoscon and diyHue gateways implement a largely compatible
+/// REST API, but differ in discovery, registration and some fields.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum BridgeProfile {
+
+    /// Philips Hue bridge.
+    #[default]
+    Philips,
+
+    /// dresden elektronik deCONZ/Phoscon gateway.
+    Deconz,
+
+    /// diyHue emulated bridge.
+    DiyHue,
+}
+
+impl fmt::Display for BridgeProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BridgeProfile::Philips => "Philips Hue",
+            BridgeProfile::Deconz => "deCONZ",
+            BridgeProfile::DiyHue => "diyHue",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl BridgeProfile {
+
+    /// Guess the profile from the fields of a bridge description.
+    ///
+    /// Compatible gateways often mimic the Philips description,
+    /// so this falls back to `Philips` if nothing stands out.
+    pub fn from_description(manufacturer: &str, model: &str, name: &str) -> Self {
+        let text = format!("{} {} {}", manufacturer, model, name).to_lowercase();
+        if text.contains("deconz") || text.contains("phoscon") || text.contains("dresden elektronik") {
+            BridgeProfile::Deconz
+        } else if text.contains("diyhue") {
+            BridgeProfile::DiyHue
+        } else {
+            BridgeProfile::Philips
+        }
+    }
+
+    /// Guess the profile from the public bridge configuration.
+    ///
+    /// deCONZ reports the fixed model ID `deCONZ`, while diyHue
+    /// can only be told apart by its default name.
+    pub fn from_config(model_id: Option<&str>, name: &str) -> Self {
+        match model_id {
+            Some(model_id) if model_id.eq_ignore_ascii_case("deconz") => BridgeProfile::Deconz,
+            _ => Self::from_description("", model_id.unwrap_or(""), name),
+        }
+    }
+
+    /// Get the infix between OUI and device part of the bridge ID.
+    pub(crate) fn bridge_id_infix(self) -> &'static str {
+        match self {
+            BridgeProfile::Deconz => "FFFF",
+            _ => "FFFE",
+        }
+    }
+
+    /// Get the instructions for allowing a new user to register.
+    pub fn get_pairing_hint(self) -> &'static str {
+        match self {
+            BridgeProfile::Philips => "Please press the link button on the bridge.",
+            BridgeProfile::Deconz => "Please unlock the gateway in the Phoscon app (Gateway > Advanced > Authenticate app).",
+            BridgeProfile::DiyHue => "Please press the link button in the diyHue web interface.",
+        }
+    }
+}
+
+/// Test whether two bridge IDs belong to the same bridge.
+///
+/// Bridge IDs are derived from the MAC address, but gateways disagree
+/// on the infix, so only the OUI and the device part are compared.
+pub(crate) fn bridge_ids_match(a: &str, b: &str) -> bool {
+    if a.eq_ignore_ascii_case(b) {
+        return true;
+    }
+    a.len() == 16 && b.len() == 16 && a.is_ascii() && b.is_ascii()
+        && a[..6].eq_ignore_ascii_case(&b[..6])
+        && a[10..].eq_ignore_ascii_case(&b[10..])
+}
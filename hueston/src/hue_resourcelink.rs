@@ -0,0 +1,265 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{HueBridgeClient, HueError, HueSensor};
+
+/// Typed reference to a bridge resource.
+///
+/// References are (de)serialized as resource addresses like `/lights/1`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HueResourceRef {
+    Light(usize),
+    Group(usize),
+    Scene(String),
+    Rule(usize),
+    Sensor(usize),
+    Schedule(usize),
+    ResourceLink(usize),
+
+    /// Any other resource address
+    Other(String),
+}
+
+impl HueResourceRef {
+
+    /// Parse a resource address like `/groups/1`.
+    pub fn parse(address: &str) -> Self {
+        let mut parts = address.trim_start_matches('/').splitn(2, '/');
+        let kind = parts.next().unwrap_or("");
+        let id = parts.next().unwrap_or("");
+        match (kind, id.parse::<usize>()) {
+            ("lights", Ok(id)) => HueResourceRef::Light(id),
+            ("groups", Ok(id)) => HueResourceRef::Group(id),
+            ("rules", Ok(id)) => HueResourceRef::Rule(id),
+            ("sensors", Ok(id)) => HueResourceRef::Sensor(id),
+            ("schedules", Ok(id)) => HueResourceRef::Schedule(id),
+            ("resourcelinks", Ok(id)) => HueResourceRef::ResourceLink(id),
+            ("scenes", _) if !id.is_empty() && !id.contains('/') => {
+                HueResourceRef::Scene(id.to_string())
+            }
+            _ => HueResourceRef::Other(address.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for HueResourceRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HueResourceRef::Light(id) => write!(f, "/lights/{}", id),
+            HueResourceRef::Group(id) => write!(f, "/groups/{}", id),
+            HueResourceRef::Scene(id) => write!(f, "/scenes/{}", id),
+            HueResourceRef::Rule(id) => write!(f, "/rules/{}", id),
+            HueResourceRef::Sensor(id) => write!(f, "/sensors/{}", id),
+            HueResourceRef::Schedule(id) => write!(f, "/schedules/{}", id),
+            HueResourceRef::ResourceLink(id) => write!(f, "/resourcelinks/{}", id),
+            HueResourceRef::Other(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+impl Serialize for HueResourceRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for HueResourceRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        Ok(HueResourceRef::parse(&address))
+    }
+}
+
+/// Parameters for creating or updating a resource link.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueResourceLinkParams {
+
+    /// Resource link name
+    pub name: String,
+
+    /// Resource link description
+    pub description: String,
+
+    /// Class ID, defined by the creating application
+    #[serde(rename = "classid")]
+    pub class_id: u32,
+
+    /// Whether the bridge may delete the resource link
+    pub recycle: bool,
+
+    /// Linked resources
+    pub links: Vec<HueResourceRef>,
+}
+
+/// Hue Resource Link.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueResourceLink {
+
+    /// Resource link ID on the bridge
+    #[serde(default)]
+    id: usize,
+
+    /// Resource link name
+    name: String,
+
+    /// Resource link description
+    #[serde(default)]
+    description: String,
+
+    /// Class ID, defined by the creating application
+    #[serde(rename = "classid")]
+    class_id: u32,
+
+    /// Username of the creator
+    owner: Option<String>,
+
+    /// Whether the bridge may delete the resource link
+    #[serde(default)]
+    recycle: bool,
+
+    /// Linked resources
+    links: Vec<HueResourceRef>,
+}
+
+impl HueResourceLink {
+
+    /// Get the resource link ID on the bridge.
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the resource link name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the resource link description.
+    pub fn get_description(&self) -> &String {
+        &self.description
+    }
+
+    /// Get the class ID.
+    pub fn get_class_id(&self) -> u32 {
+        self.class_id
+    }
+
+    /// Get the username of the creator.
+    pub fn get_owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// Test whether the bridge may delete the resource link.
+    pub fn is_recycle(&self) -> bool {
+        self.recycle
+    }
+
+    /// Get the linked resources.
+    pub fn get_links(&self) -> &[HueResourceRef] {
+        &self.links
+    }
+
+    /// Set the resource link ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+}
+
+impl HueBridgeClient {
+
+    /// Fetch all resource links.
+    pub fn fetch_resourcelinks(&self) -> Result<Vec<HueResourceLink>, HueError> {
+        let ep = self.get_user_endpoint("/resourcelinks")?;
+        let hm: BTreeMap<usize, HueResourceLink> = self.get(&ep)?;
+        Ok(hm.into_iter().map(|(k, mut v)| { v.set_id(k); v }).collect())
+    }
+
+    /// Fetch a single resource link.
+    pub fn fetch_resourcelink(&self, id: usize) -> Result<HueResourceLink, HueError> {
+        let ep = self.get_user_endpoint(&format!("/resourcelinks/{}", id))?;
+        let mut link: HueResourceLink = self.get(&ep)?;
+        link.set_id(id);
+        Ok(link)
+    }
+
+    /// Create a resource link and return its ID.
+    pub fn create_resourcelink(&self, params: &HueResourceLinkParams) -> Result<usize, HueError> {
+        let id = self.create("/resourcelinks", params)?;
+        id.parse().map_err(|_| HueError::UnexpectedResponse(id.into()))
+    }
+
+    /// Update the name, description, class ID and links of a resource link.
+    pub fn update_resourcelink(&self, id: usize, params: &HueResourceLinkParams) -> Result<(), HueError> {
+        let ep = self.get_user_endpoint(&format!("/resourcelinks/{}", id))?;
+
+        // The recycle flag cannot be changed
+        let body = serde_json::json!({
+            "name": params.name,
+            "description": params.description,
+            "classid": params.class_id,
+            "links": params.links,
+        });
+        self.put(&ep, &body)?;
+        Ok(())
+    }
+
+    /// Delete a resource link.
+    ///
+    /// The linked resources are kept.
+    pub fn delete_resourcelink(&self, id: usize) -> Result<(), HueError> {
+        let ep = self.get_user_endpoint(&format!("/resourcelinks/{}", id))?;
+        self.delete(&ep)?;
+        Ok(())
+    }
+
+    /// Delete a resource link together with the resources it links.
+    ///
+    /// Only resources that exist for the sake of the link are cascaded:
+    /// rules, scenes, schedules and CLIP sensors, i.e. virtual sensors
+    /// created by applications. Lights and physical sensors are never
+    /// deleted, since that would unpair them, and neither are groups,
+    /// which are the rooms and zones of the user. Nested resource links
+    /// and unknown addresses are kept as well.
+    ///
+    /// All cascaded resources are deleted, even if some of them fail.
+    /// The first error is returned.
+    pub fn delete_resourcelink_recursive(&self, id: usize) -> Result<(), HueError> {
+        let link = self.fetch_resourcelink(id)?;
+        let mut result = Ok(());
+        for resource in link.get_links() {
+            let deleted = self.is_cascaded(resource).and_then(|cascaded| {
+                if !cascaded {
+                    return Ok(());
+                }
+                let ep = self.get_user_endpoint(&resource.to_string())?;
+                self.delete(&ep)?;
+                Ok(())
+            });
+            if let Err(err) = deleted {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        self.delete_resourcelink(id)?;
+        result
+    }
+
+    /// Test whether a linked resource is deleted along with its resource link.
+    fn is_cascaded(&self, resource: &HueResourceRef) -> Result<bool, HueError> {
+        match resource {
+            HueResourceRef::Rule(_) | HueResourceRef::Scene(_) | HueResourceRef::Schedule(_) => Ok(true),
+            HueResourceRef::Sensor(id) => {
+                let ep = self.get_user_endpoint(&format!("/sensors/{}", id))?;
+                let sensor: HueSensor = self.get(&ep)?;
+                Ok(sensor.get_type().starts_with("CLIP"))
+            }
+            HueResourceRef::Light(_)
+            | HueResourceRef::Group(_)
+            | HueResourceRef::ResourceLink(_)
+            | HueResourceRef::Other(_) => Ok(false),
+        }
+    }
+}
@@ -1,5 +1,19 @@
+use serde::de::{Deserialize, Deserializer};
 use serde_derive::Deserialize;
 
+use super::HueError;
+
+/// Deserialize a field that compatible gateways may report as `null`.
+///
+/// Use together with `#[serde(default)]` for fields they omit entirely.
+pub(crate) fn null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HueBridgeRegistration {
     success: Option<HueBridgeRegistrationSuccess>,
@@ -11,6 +25,15 @@ pub struct HueBridgeRegistrationSuccess {
     pub username: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HueBridgePublicConfig {
+    pub name: String,
+    #[serde(rename = "bridgeid")]
+    pub bridge_id: String,
+    #[serde(rename = "modelid")]
+    pub model_id: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HueBridgeError {
     r#type: i32,
@@ -21,16 +44,75 @@ pub struct HueBridgeError {
 impl HueBridgeError {
 
     pub fn error_code(&self) -> HueErrorCode {
-        match self.r#type {
-            101 => HueErrorCode::LinkButtonNotPressed,
-            _ => HueErrorCode::Unknown,
+        HueErrorCode::from_type(self.r#type)
+    }
+}
+
+impl From<HueBridgeError> for HueError {
+    fn from(err: HueBridgeError) -> Self {
+        HueError::Bridge {
+            code: err.r#type,
+            address: err.address,
+            description: err.description,
         }
     }
 }
 
+/// A single entry of a bridge response array.
+#[derive(Debug, Deserialize)]
+pub struct HueBridgeStatus {
+    error: Option<HueBridgeError>,
+}
+
+impl HueBridgeStatus {
+
+    /// Extract the ID of a created resource from a bridge response.
+    pub fn created_id(value: &serde_json::Value) -> Option<String> {
+        let id = value.get(0)?.get("success")?.get("id")?;
+        match id {
+            serde_json::Value::String(id) => Some(id.clone()),
+            serde_json::Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Find the first error in a bridge response.
+    pub fn first_error(value: &serde_json::Value) -> Option<HueError> {
+        let statuses = value.as_array()?;
+        statuses
+            .iter()
+            .filter_map(|status| serde_json::from_value::<HueBridgeStatus>(status.clone()).ok())
+            .filter_map(|status| status.error)
+            .next()
+            .map(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HueErrorCode {
     Unknown,
+    Unauthorized,
+    ResourceNotAvailable,
+    ParameterNotModifiable,
     LinkButtonNotPressed,
+    DeviceOff,
+    InternalError,
+}
+
+impl HueErrorCode {
+
+    /// Map a bridge error type to an error code.
+    pub fn from_type(r#type: i32) -> Self {
+        match r#type {
+            1 => HueErrorCode::Unauthorized,
+            3 => HueErrorCode::ResourceNotAvailable,
+            8 => HueErrorCode::ParameterNotModifiable,
+            101 => HueErrorCode::LinkButtonNotPressed,
+            201 => HueErrorCode::DeviceOff,
+            901 => HueErrorCode::InternalError,
+            _ => HueErrorCode::Unknown,
+        }
+    }
 }
 
 impl HueBridgeRegistration {
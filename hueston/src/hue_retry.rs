@@ -0,0 +1,204 @@
+use rand::Rng;
+use std::time::Duration;
+
+use super::HueError;
+
+/// Condition under which a failed request is retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetryCondition {
+
+    /// The request could not be sent or the response could not be read.
+    Transport,
+
+    /// The bridge answered with the given HTTP status code.
+    HttpStatus(u16),
+
+    /// The bridge reported an error of the given type.
+    BridgeError(i32),
+}
+
+impl RetryCondition {
+
+    /// Test whether the condition matches an error.
+    pub fn matches(&self, err: &HueError) -> bool {
+        match (self, err) {
+            (RetryCondition::Transport, err) if err.is_transport() => true,
+            (RetryCondition::HttpStatus(code), HueError::Status(status)) => code == status,
+            (RetryCondition::BridgeError(code), HueError::Bridge { code: other, .. }) => code == other,
+            _ => false,
+        }
+    }
+}
+
+/// Retry policy with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+
+    /// Maximum number of attempts, including the first one.
+    max_attempts: u32,
+
+    /// Delay before the first retry.
+    base_delay: Duration,
+
+    /// Upper bound for the delay between two attempts.
+    max_delay: Duration,
+
+    /// Whether to randomize delays.
+    jitter: bool,
+
+    /// Conditions under which a request is retried.
+    retry_on: Vec<RetryCondition>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            jitter: true,
+            retry_on: vec![
+                RetryCondition::Transport,
+                RetryCondition::HttpStatus(503),
+                RetryCondition::BridgeError(901),
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+
+    /// Construct a policy that never retries.
+    pub fn none() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Set the maximum number of attempts, including the first one.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry.
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Set the upper bound for the delay between two attempts.
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Enable or disable randomized delays.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the conditions under which a request is retried.
+    pub fn with_retry_on(mut self, retry_on: Vec<RetryCondition>) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Get the maximum number of attempts.
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Test whether a failed attempt should be retried.
+    ///
+    /// After a transport error it is unknown whether the bridge processed
+    /// the request, so these are only retried for idempotent requests.
+    /// Otherwise a timeout after creating a resource would create it twice.
+    pub fn should_retry(&self, attempt: u32, err: &HueError, idempotent: bool) -> bool {
+        if !idempotent && RetryCondition::Transport.matches(err) {
+            return false;
+        }
+        attempt < self.max_attempts && self.retry_on.iter().any(|cond| cond.matches(err))
+    }
+
+    /// Get the delay after the given failed attempt.
+    ///
+    /// The delay doubles with every attempt. With jitter enabled,
+    /// a random delay between half and the full value is picked.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        let millis = delay.as_secs() * 1000 + u64::from(delay.subsec_millis());
+        let half = millis / 2;
+        Duration::from_millis(half + rand::thread_rng().gen_range(0, millis - half + 1))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeout() -> HueError {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out").into()
+    }
+
+    fn bridge_error(code: i32) -> HueError {
+        HueError::Bridge {
+            code,
+            address: "/lights/1/state".to_string(),
+            description: "error".to_string(),
+        }
+    }
+
+    #[test]
+    fn retries_matching_errors_until_the_last_attempt() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(1, &timeout(), true));
+        assert!(policy.should_retry(2, &HueError::Status(503), true));
+        assert!(policy.should_retry(2, &bridge_error(901), true));
+        assert!(!policy.should_retry(3, &timeout(), true));
+
+        // Other errors fail right away
+        assert!(!policy.should_retry(1, &HueError::Status(404), true));
+        assert!(!policy.should_retry(1, &bridge_error(7), true));
+        assert!(!policy.should_retry(1, &HueError::NotRegistered, true));
+        assert!(!RetryPolicy::none().should_retry(1, &timeout(), true));
+    }
+
+    #[test]
+    fn retries_transport_errors_only_if_idempotent() {
+        let policy = RetryPolicy::default();
+        assert!(!policy.should_retry(1, &timeout(), false));
+
+        // The bridge refused these requests, so resending is safe
+        assert!(policy.should_retry(1, &HueError::Status(503), false));
+        assert!(policy.should_retry(1, &bridge_error(901), false));
+    }
+
+    #[test]
+    fn doubles_delays_up_to_the_maximum() {
+        let policy = RetryPolicy::default()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(500))
+            .with_jitter(false);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitters_between_half_and_the_full_delay() {
+        let policy = RetryPolicy::default().with_base_delay(Duration::from_millis(100));
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200), "{:?}", delay);
+        }
+    }
+}
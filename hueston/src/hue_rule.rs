@@ -0,0 +1,126 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::hue_schedule::HueCommand;
+
+/// Condition of a rule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueRuleCondition {
+
+    /// Resource attribute address
+    /// E.g. '/sensors/2/state/buttonevent'
+    address: String,
+
+    /// Operator
+    /// E.g. 'eq', 'gt', 'dx' or 'in'
+    operator: String,
+
+    /// Value to compare with
+    value: Option<String>,
+}
+
+impl HueRuleCondition {
+
+    /// Get the resource attribute address.
+    pub fn get_address(&self) -> &String {
+        &self.address
+    }
+
+    /// Get the operator.
+    pub fn get_operator(&self) -> &String {
+        &self.operator
+    }
+
+    /// Get the value to compare with.
+    pub fn get_value(&self) -> Option<&String> {
+        self.value.as_ref()
+    }
+}
+
+/// Hue Rule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueRule {
+
+    /// Rule ID on the bridge
+    #[serde(default)]
+    id: usize,
+
+    /// Rule name
+    name: String,
+
+    /// Username of the creator
+    owner: Option<String>,
+
+    /// Creation time
+    created: Option<String>,
+
+    /// Time the rule was last triggered
+    #[serde(rename = "lasttriggered")]
+    last_triggered: Option<String>,
+
+    /// Number of times the rule was triggered
+    #[serde(rename = "timestriggered", default)]
+    times_triggered: u32,
+
+    /// Status
+    /// Either 'enabled', 'disabled' or 'resourcedeleted'
+    status: Option<String>,
+
+    /// Conditions, all of which need to be met
+    conditions: Vec<HueRuleCondition>,
+
+    /// Actions to execute
+    actions: Vec<HueCommand>,
+}
+
+impl HueRule {
+
+    /// Get the rule ID on the bridge.
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the rule name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the username of the creator.
+    pub fn get_owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// Get the creation time.
+    pub fn get_created(&self) -> Option<&String> {
+        self.created.as_ref()
+    }
+
+    /// Get the time the rule was last triggered.
+    pub fn get_last_triggered(&self) -> Option<&String> {
+        self.last_triggered.as_ref()
+    }
+
+    /// Get the number of times the rule was triggered.
+    pub fn get_times_triggered(&self) -> u32 {
+        self.times_triggered
+    }
+
+    /// Test whether the rule is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.status.as_ref().is_none_or(|status| status == "enabled")
+    }
+
+    /// Get the conditions.
+    pub fn get_conditions(&self) -> &[HueRuleCondition] {
+        &self.conditions
+    }
+
+    /// Get the actions.
+    pub fn get_actions(&self) -> &[HueCommand] {
+        &self.actions
+    }
+
+    /// Set the rule ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+}
\ No newline at end of file
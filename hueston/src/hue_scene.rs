@@ -0,0 +1,103 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Hue Scene.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueScene {
+
+    /// Scene ID on the bridge
+    #[serde(default)]
+    id: String,
+
+    /// Scene name
+    name: String,
+
+    /// Scene type
+    /// Either 'LightScene' or 'GroupScene'
+    #[serde(rename = "type")]
+    r#type: Option<String>,
+
+    /// ID of the group, for group scenes
+    group: Option<String>,
+
+    /// IDs of the lights in the scene
+    lights: Vec<String>,
+
+    /// Username of the creator
+    owner: Option<String>,
+
+    /// Whether the bridge may delete the scene
+    #[serde(default)]
+    recycle: bool,
+
+    /// Whether the scene is used by a rule or schedule
+    #[serde(default)]
+    locked: bool,
+
+    /// Time of the last update
+    #[serde(rename = "lastupdated")]
+    last_updated: Option<String>,
+
+    /// Light states, by light ID
+    /// Only available when fetching a single scene
+    #[serde(rename = "lightstates")]
+    light_states: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+impl HueScene {
+
+    /// Get the scene ID on the bridge.
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    /// Get the scene name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the scene type.
+    pub fn get_type(&self) -> Option<&String> {
+        self.r#type.as_ref()
+    }
+
+    /// Get the ID of the group, for group scenes.
+    pub fn get_group(&self) -> Option<&String> {
+        self.group.as_ref()
+    }
+
+    /// Get the IDs of the lights in the scene.
+    pub fn get_light_ids(&self) -> &[String] {
+        &self.lights
+    }
+
+    /// Get the username of the creator.
+    pub fn get_owner(&self) -> Option<&String> {
+        self.owner.as_ref()
+    }
+
+    /// Test whether the bridge may delete the scene.
+    pub fn is_recycle(&self) -> bool {
+        self.recycle
+    }
+
+    /// Test whether the scene is used by a rule or schedule.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Get the time of the last update.
+    pub fn get_last_updated(&self) -> Option<&String> {
+        self.last_updated.as_ref()
+    }
+
+    /// Get the light states, by light ID.
+    pub fn get_light_states(&self) -> Option<&BTreeMap<String, serde_json::Value>> {
+        self.light_states.as_ref()
+    }
+
+    /// Set the scene ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+}
\ No newline at end of file
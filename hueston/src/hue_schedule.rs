@@ -0,0 +1,116 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Command executed by a schedule or rule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueCommand {
+
+    /// Resource address
+    /// E.g. '/api/<username>/groups/1/action'
+    address: String,
+
+    /// HTTP method
+    method: String,
+
+    /// Request body
+    body: serde_json::Value,
+}
+
+impl HueCommand {
+
+    /// Get the resource address.
+    pub fn get_address(&self) -> &String {
+        &self.address
+    }
+
+    /// Get the HTTP method.
+    pub fn get_method(&self) -> &String {
+        &self.method
+    }
+
+    /// Get the request body.
+    pub fn get_body(&self) -> &serde_json::Value {
+        &self.body
+    }
+}
+
+/// Hue Schedule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueSchedule {
+
+    /// Schedule ID on the bridge
+    #[serde(default)]
+    id: usize,
+
+    /// Schedule name
+    name: String,
+
+    /// Schedule description
+    #[serde(default)]
+    description: String,
+
+    /// Command to execute
+    command: HueCommand,
+
+    /// Local time the schedule is triggered at
+    #[serde(rename = "localtime")]
+    local_time: Option<String>,
+
+    /// Creation time
+    created: Option<String>,
+
+    /// Status
+    /// Either 'enabled' or 'disabled'
+    status: Option<String>,
+
+    /// Whether the schedule is deleted after it expired
+    #[serde(rename = "autodelete")]
+    auto_delete: Option<bool>,
+}
+
+impl HueSchedule {
+
+    /// Get the schedule ID on the bridge.
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the schedule name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the schedule description.
+    pub fn get_description(&self) -> &String {
+        &self.description
+    }
+
+    /// Get the command to execute.
+    pub fn get_command(&self) -> &HueCommand {
+        &self.command
+    }
+
+    /// Get the local time the schedule is triggered at.
+    pub fn get_local_time(&self) -> Option<&String> {
+        self.local_time.as_ref()
+    }
+
+    /// Get the creation time.
+    pub fn get_created(&self) -> Option<&String> {
+        self.created.as_ref()
+    }
+
+    /// Test whether the schedule is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.status.as_ref().is_none_or(|status| status == "enabled")
+    }
+
+    /// Test whether the schedule is deleted after it expired.
+    pub fn is_auto_delete(&self) -> bool {
+        self.auto_delete.unwrap_or(false)
+    }
+
+    /// Set the schedule ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::HueDeviceUpdate;
+
+/// Hue Sensor.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueSensor {
+
+    /// Sensor ID on the bridge
+    #[serde(default)]
+    id: usize,
+
+    /// Sensor name
+    name: String,
+
+    /// Sensor type
+    /// E.g. 'ZLLPresence', 'Daylight' or 'CLIPGenericStatus'
+    #[serde(rename = "type")]
+    r#type: String,
+
+    /// Hardware model
+    #[serde(rename = "modelid", default, deserialize_with = "crate::hue_resp::null_default")]
+    model_id: String,
+
+    /// Manufacturer name
+    #[serde(rename = "manufacturername", default, deserialize_with = "crate::hue_resp::null_default")]
+    manufacturer_name: String,
+
+    /// Unique ID
+    /// Not available for virtual sensors
+    #[serde(rename = "uniqueid")]
+    unique_id: Option<String>,
+
+    /// Sensor state, depends on the sensor type
+    state: serde_json::Value,
+
+    /// Sensor configuration, depends on the sensor type
+    config: serde_json::Value,
+
+    /// Software version
+    #[serde(rename = "swversion")]
+    sw_version: Option<String>,
+
+    /// Software update information
+    /// Only available for physical sensors
+    #[serde(rename = "swupdate")]
+    sw_update: Option<HueDeviceUpdate>,
+}
+
+impl HueSensor {
+
+    /// Get the sensor ID on the bridge.
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the sensor name.
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the sensor type.
+    pub fn get_type(&self) -> &String {
+        &self.r#type
+    }
+
+    /// Get the hardware model.
+    pub fn get_model_id(&self) -> &String {
+        &self.model_id
+    }
+
+    /// Get the manufacturer name.
+    pub fn get_manufacturer_name(&self) -> &String {
+        &self.manufacturer_name
+    }
+
+    /// Get the unique ID.
+    pub fn get_unique_id(&self) -> Option<&String> {
+        self.unique_id.as_ref()
+    }
+
+    /// Get the sensor state.
+    pub fn get_state(&self) -> &serde_json::Value {
+        &self.state
+    }
+
+    /// Get the sensor configuration.
+    pub fn get_config(&self) -> &serde_json::Value {
+        &self.config
+    }
+
+    /// Get the software version.
+    pub fn get_sw_version(&self) -> Option<&String> {
+        self.sw_version.as_ref()
+    }
+
+    /// Get the software update information.
+    pub fn get_sw_update(&self) -> Option<&HueDeviceUpdate> {
+        self.sw_update.as_ref()
+    }
+
+    /// Set the sensor ID on the bridge.
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+}
\ No newline at end of file
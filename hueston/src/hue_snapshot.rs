@@ -0,0 +1,212 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+
+use super::{HueError, HueHome, HueLight, HueLightBatch};
+
+/// Captured state of a single light.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct LightSnapshotEntry {
+    id: usize,
+    on: bool,
+    bri: u8,
+    colormode: Option<String>,
+    xy: [f32; 2],
+    ct: u16,
+    hue: u16,
+    sat: u8,
+}
+
+impl LightSnapshotEntry {
+
+    /// Capture the state of a light.
+    fn capture(light: &HueLight) -> Self {
+        Self {
+            id: light.get_id(),
+            on: light.is_on(),
+            bri: light.get_brightness(),
+            colormode: light.get_color_mode().cloned(),
+            xy: light.get_xy(),
+            ct: light.get_temperature(),
+            hue: light.get_hue(),
+            sat: light.get_saturation(),
+        }
+    }
+
+    /// Build the command batches that restore the state.
+    ///
+    /// Only attributes the light supports are restored. A brightness of
+    /// zero means the light cannot be dimmed, and without a color mode the
+    /// light has no color. Sending them anyway would make the bridge
+    /// reject the whole batch.
+    ///
+    /// Attributes cannot be changed while a light is off, so lights that
+    /// were off are switched on to restore their color, and switched off
+    /// again by the second batch. Otherwise the color left by an effect
+    /// would show the next time the light is switched on.
+    fn to_batches(&self, transition_time: u16) -> (HueLightBatch, Option<HueLightBatch>) {
+        let mut batch = HueLightBatch::new(self.id);
+        batch.transition_time(transition_time);
+
+        // Restore the color in the active color mode
+        let dimmable = self.bri > 0;
+        if dimmable {
+            batch.brightness(self.bri);
+        }
+        let colored = match self.colormode.as_deref() {
+            Some("xy") => {
+                batch.xy(self.xy);
+                true
+            }
+            Some("ct") => {
+                batch.temperature(self.ct);
+                true
+            }
+            Some("hs") => {
+                batch.hue(self.hue);
+                batch.saturation(self.sat);
+                true
+            }
+            _ => false,
+        };
+
+        // Lights without brightness or color, like plugs, only need to be switched
+        if self.on || !(dimmable || colored) {
+            batch.on(self.on);
+            return (batch, None);
+        }
+        batch.on(true);
+
+        // Switch the light off again
+        let mut off = HueLightBatch::new(self.id);
+        off.on(false);
+        off.transition_time(transition_time);
+        (batch, Some(off))
+    }
+}
+
+/// Snapshot of the state of a set of lights.
+///
+/// Effects can take a snapshot before they start,
+/// and restore the previous look once they are done.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LightSnapshot {
+    states: BTreeMap<String, LightSnapshotEntry>,
+}
+
+impl LightSnapshot {
+
+    /// Capture the state of the given lights of a home.
+    ///
+    /// The state is taken from the last `HueHome::refresh`.
+    /// Unknown lights are ignored.
+    pub fn capture(home: &HueHome, lights: &[String]) -> Self {
+        let states = lights
+            .iter()
+            .filter_map(|uid| {
+                let light = home.light(uid)?;
+                Some((uid.clone(), LightSnapshotEntry::capture(light)))
+            })
+            .collect();
+        Self { states }
+    }
+
+    /// Get the unique IDs of the captured lights.
+    pub fn lights(&self) -> impl Iterator<Item = &String> {
+        self.states.keys()
+    }
+
+    /// Restore the captured state.
+    ///
+    /// The transition time is given in multiples of 100ms. Lights that
+    /// were off are switched off once their color is restored, which
+    /// takes another transition. They are switched off even if their
+    /// color could not be restored.
+    ///
+    /// All lights are restored, even if some of them fail.
+    /// The first error is returned.
+    pub fn restore(&self, home: &HueHome, transition_time: u16) -> Result<(), HueError> {
+        let mut result = Ok(());
+        let mut off_batches = Vec::new();
+        for (uid, state) in &self.states {
+            let (batch, off) = state.to_batches(transition_time);
+            let (_, params) = batch.build();
+            if let Err(err) = home.set_light_state(uid, &params) {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+
+            // Lights that were off are switched off even if their color failed
+            off_batches.extend(off.map(|off| (uid, off)));
+        }
+        if off_batches.is_empty() {
+            return result;
+        }
+
+        // Wait for the color transition before switching off
+        thread::sleep(Duration::from_millis(u64::from(transition_time) * 100));
+        for (uid, off) in off_batches {
+            let (_, params) = off.build();
+            if let Err(err) = home.set_light_state(uid, &params) {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Build a snapshot entry with the given brightness and color mode.
+    fn entry(on: bool, bri: u8, colormode: Option<&str>) -> LightSnapshotEntry {
+        LightSnapshotEntry {
+            id: 3,
+            on,
+            bri,
+            colormode: colormode.map(String::from),
+            xy: [0.5, 0.4],
+            ct: 366,
+            hue: 0,
+            sat: 0,
+        }
+    }
+
+    /// Get the parameters of the batches, sorted by name.
+    fn params(batches: (HueLightBatch, Option<HueLightBatch>)) -> (Vec<(&'static str, serde_json::Value)>, bool) {
+        let (batch, off) = batches;
+        let mut params: Vec<_> = batch.build().1.into_iter().collect();
+        params.sort_by_key(|(name, _)| *name);
+        (params, off.is_some())
+    }
+
+    #[test]
+    fn restores_only_supported_attributes() {
+        let (color, off) = params(entry(true, 144, Some("ct")).to_batches(4));
+        assert_eq!(color, vec![("bri", json!(144)), ("ct", json!(366)), ("on", json!(true)), ("transitiontime", json!(4))]);
+        assert!(!off);
+
+        // Plugs report neither brightness nor color
+        let (color, off) = params(entry(true, 0, None).to_batches(4));
+        assert_eq!(color, vec![("on", json!(true)), ("transitiontime", json!(4))]);
+        assert!(!off);
+    }
+
+    #[test]
+    fn switches_lights_that_were_off_back_off() {
+        let (color, off) = params(entry(false, 144, Some("ct")).to_batches(4));
+        assert_eq!(color, vec![("bri", json!(144)), ("ct", json!(366)), ("on", json!(true)), ("transitiontime", json!(4))]);
+        assert!(off);
+
+        // Nothing to restore, so the plug is switched off right away
+        let (color, off) = params(entry(false, 0, None).to_batches(4));
+        assert_eq!(color, vec![("on", json!(false)), ("transitiontime", json!(4))]);
+        assert!(!off);
+    }
+}
@@ -0,0 +1,208 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::hue_features::HueApiVersion;
+use super::{HueBridgeClient, HueError};
+
+/// First API version supporting `swupdate2`.
+pub const API_SWUPDATE2: HueApiVersion = HueApiVersion::new(1, 20, 0);
+
+/// Software update state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HueUpdateState {
+
+    /// The software is up to date.
+    NoUpdates,
+
+    /// An update is being downloaded.
+    Transferring,
+
+    /// An update is ready to install.
+    ReadyToInstall,
+
+    /// Some devices have updates ready to install.
+    AnyReadyToInstall,
+
+    /// All devices have updates ready to install.
+    AllReadyToInstall,
+
+    /// An update is being installed.
+    Installing,
+
+    /// The device cannot be updated.
+    NotUpdatable,
+
+    /// Any other state.
+    Unknown,
+}
+
+impl HueUpdateState {
+
+    /// Parse an update state as reported by the bridge.
+    pub fn parse(state: &str) -> Self {
+        match state {
+            "noupdates" => HueUpdateState::NoUpdates,
+            "transferring" => HueUpdateState::Transferring,
+            "readytoinstall" => HueUpdateState::ReadyToInstall,
+            "anyreadytoinstall" => HueUpdateState::AnyReadyToInstall,
+            "allreadytoinstall" => HueUpdateState::AllReadyToInstall,
+            "installing" => HueUpdateState::Installing,
+            "notupdatable" => HueUpdateState::NotUpdatable,
+            _ => HueUpdateState::Unknown,
+        }
+    }
+
+    /// Test whether a newer software version is available.
+    pub fn is_outdated(self) -> bool {
+        matches!(
+            self,
+            HueUpdateState::Transferring
+                | HueUpdateState::ReadyToInstall
+                | HueUpdateState::AnyReadyToInstall
+                | HueUpdateState::AllReadyToInstall
+                | HueUpdateState::Installing
+        )
+    }
+}
+
+/// Software update information of a device.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueDeviceUpdate {
+
+    /// Update state
+    state: String,
+
+    /// Time of the last installation
+    #[serde(rename = "lastinstall")]
+    last_install: Option<String>,
+}
+
+impl HueDeviceUpdate {
+
+    /// Get the update state.
+    pub fn get_state(&self) -> HueUpdateState {
+        HueUpdateState::parse(&self.state)
+    }
+
+    /// Get the time of the last installation.
+    pub fn get_last_install(&self) -> Option<&String> {
+        self.last_install.as_ref()
+    }
+}
+
+/// Automatic installation settings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueAutoInstall {
+
+    /// Whether updates are installed automatically
+    on: bool,
+
+    /// Time of day updates are installed at
+    /// E.g. 'T14:00:00'
+    #[serde(rename = "updatetime")]
+    update_time: Option<String>,
+}
+
+impl HueAutoInstall {
+
+    /// Test whether updates are installed automatically.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Get the time of day updates are installed at.
+    pub fn get_update_time(&self) -> Option<&String> {
+        self.update_time.as_ref()
+    }
+}
+
+/// Software update information of a bridge and its devices.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HueSwUpdate {
+
+    /// Whether the bridge is checking for updates
+    #[serde(rename = "checkforupdate", default)]
+    check_for_update: bool,
+
+    /// Time of the last state change
+    #[serde(rename = "lastchange")]
+    last_change: Option<String>,
+
+    /// Update information of the bridge itself
+    bridge: HueDeviceUpdate,
+
+    /// Update state of the whole system
+    state: String,
+
+    /// Automatic installation settings
+    #[serde(rename = "autoinstall")]
+    auto_install: HueAutoInstall,
+}
+
+impl HueSwUpdate {
+
+    /// Test whether the bridge is checking for updates.
+    pub fn is_checking(&self) -> bool {
+        self.check_for_update
+    }
+
+    /// Get the time of the last state change.
+    pub fn get_last_change(&self) -> Option<&String> {
+        self.last_change.as_ref()
+    }
+
+    /// Get the update information of the bridge itself.
+    pub fn get_bridge(&self) -> &HueDeviceUpdate {
+        &self.bridge
+    }
+
+    /// Get the update state of the whole system.
+    pub fn get_state(&self) -> HueUpdateState {
+        HueUpdateState::parse(&self.state)
+    }
+
+    /// Get the automatic installation settings.
+    pub fn get_auto_install(&self) -> &HueAutoInstall {
+        &self.auto_install
+    }
+}
+
+impl HueBridgeClient {
+
+    /// Fetch the software update information.
+    pub fn fetch_sw_update(&self) -> Result<HueSwUpdate, HueError> {
+        self.fetch_config()?
+            .into_sw_update()
+            .ok_or_else(|| HueError::Unsupported {
+                feature: "swupdate2".to_string(),
+                required: API_SWUPDATE2,
+            })
+    }
+
+    /// Update the software update settings.
+    fn put_sw_update(&self, body: serde_json::Value) -> Result<(), HueError> {
+        let ep = self.get_user_endpoint("/config")?;
+        self.put(&ep, &serde_json::json!({ "swupdate2": body }))?;
+        Ok(())
+    }
+
+    /// Make the bridge check for software updates.
+    pub fn check_for_updates(&self) -> Result<(), HueError> {
+        self.put_sw_update(serde_json::json!({ "checkforupdate": true }))
+    }
+
+    /// Install all software updates that are ready to install.
+    pub fn install_updates(&self) -> Result<(), HueError> {
+        self.put_sw_update(serde_json::json!({ "install": true }))
+    }
+
+    /// Configure the automatic installation of updates.
+    ///
+    /// The update time is given as time of day, e.g. `T14:00:00`.
+    pub fn set_auto_install(&self, on: bool, update_time: Option<&str>) -> Result<(), HueError> {
+        let mut auto_install = serde_json::json!({ "on": on });
+        if let Some(update_time) = update_time {
+            auto_install["updatetime"] = update_time.into();
+        }
+        self.put_sw_update(serde_json::json!({ "autoinstall": auto_install }))
+    }
+}
\ No newline at end of file
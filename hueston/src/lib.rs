@@ -1,11 +1,65 @@
 pub mod hue_bridge;
-pub use self::hue_bridge::HueBridge;
+pub use self::hue_bridge::{HueBridge, HueBridgeDevice};
+
+pub mod hue_config;
+pub use self::hue_config::{HueConfig, HueWhitelistEntry};
+
+pub mod hue_datastore;
+pub use self::hue_datastore::HueDatastore;
+
+pub mod hue_discovery;
+pub use self::hue_discovery::{DiscoveryEvent, DiscoveryOptions, IpVersion};
+
+pub mod hue_error;
+pub use self::hue_error::HueError;
+
+pub mod hue_archive;
+pub use self::hue_archive::{HueArchive, HueImportReport};
 
 pub mod hue_bridge_client;
-pub use self::hue_bridge_client::HueBridgeClient;
+pub use self::hue_bridge_client::{HueBridgeClient, HueBridgeClientBuilder, Relocation};
+
+pub mod hue_cassette;
+pub use self::hue_cassette::{HueCassette, HueInteraction};
+
+pub mod hue_retry;
+pub use self::hue_retry::{RetryCondition, RetryPolicy};
+
+pub mod hue_features;
+pub use self::hue_features::{BridgeFeatures, HueApiVersion, HueCapabilities, HueCapacity, HueResourceKind};
+
+pub mod hue_profile;
+pub use self::hue_profile::BridgeProfile;
+
+pub mod hue_group;
+pub use self::hue_group::HueGroup;
+
+pub mod hue_home;
+pub use self::hue_home::HueHome;
+
+pub mod hue_resourcelink;
+pub use self::hue_resourcelink::{HueResourceLink, HueResourceLinkParams, HueResourceRef};
+
+pub mod hue_rule;
+pub use self::hue_rule::{HueRule, HueRuleCondition};
+
+pub mod hue_scene;
+pub use self::hue_scene::HueScene;
+
+pub mod hue_schedule;
+pub use self::hue_schedule::{HueCommand, HueSchedule};
+
+pub mod hue_snapshot;
+pub use self::hue_snapshot::LightSnapshot;
+
+pub mod hue_swupdate;
+pub use self::hue_swupdate::{HueAutoInstall, HueDeviceUpdate, HueSwUpdate, HueUpdateState};
+
+pub mod hue_sensor;
+pub use self::hue_sensor::HueSensor;
 
 pub mod hue_light;
-pub use self::hue_light::{HueLight, HueLightBatch, HueLightState};
+pub use self::hue_light::{HueLight, HueLightBatch, HueLightCoords, HueLightState};
 
 mod hue_resp;
 pub use self::hue_resp::HueErrorCode;
@@ -15,21 +69,21 @@ pub struct Hueston;
 
 impl Hueston {
     /// Discover all Hue Bridges.
-    pub fn discover_bridges() -> Option<Vec<HueBridgeClient>> {
+    pub fn discover_bridges() -> Result<Vec<HueBridgeClient>, HueError> {
+        Self::discover_bridges_with(&DiscoveryOptions::default(), |_| ())
+    }
+
+    /// Discover all Hue Bridges with custom options.
+    ///
+    /// See `HueBridge::discover_with` for details on the callback.
+    pub fn discover_bridges_with<F>(options: &DiscoveryOptions, on_event: F) -> Result<Vec<HueBridgeClient>, HueError>
+    where
+        F: FnMut(DiscoveryEvent),
+    {
         // Discover all Hue Bridge devices
-        let bridges = match HueBridge::discover_all() {
-            Some(vec) => vec,
-            None => return None,
-        };
+        let bridges = HueBridge::discover_with(options, on_event)?;
 
         // Map the Hue Bridge devices to Hue Bridge clients
-        let vec: Vec<HueBridgeClient> = bridges.into_iter().map(HueBridgeClient::new).collect();
-
-        // Return the Hue Bridge clients
-        if vec.is_empty() {
-            None
-        } else {
-            Some(vec)
-        }
+        Ok(bridges.into_iter().map(HueBridgeClient::new).collect())
     }
 }
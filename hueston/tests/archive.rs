@@ -0,0 +1,174 @@
+use hueston::{HueArchive, HueBridgeClient};
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Get the path of a cassette fixture.
+fn cassette(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/cassettes").join(name)
+}
+
+/// Archive of a bridge whose IDs differ from the one in `import.json`.
+fn archive() -> HueArchive {
+    serde_json::from_value(json!({
+        "version": 1,
+        "bridge_id": "001788FFFE123456",
+        "lights": {
+            "1": { "name": "Living Room", "unique_id": "00:17:88:01:03:2b:3c:4d-0b" },
+            "2": { "name": "Kitchen", "unique_id": "00:17:88:01:03:2b:3c:4e-0b" },
+            "3": { "name": "Hallway", "unique_id": "00:17:88:01:03:2b:3c:4f-0b" }
+        },
+        "groups": {
+            "1": {
+                "name": "Living",
+                "type": "Room",
+                "class": "Living room",
+                "lights": ["1", "2", "3"],
+                "action": { "on": false }
+            },
+            "2": { "name": "Lamp", "type": "Luminaire", "lights": ["1"] }
+        },
+        "scenes": {
+            "abc": {
+                "name": "Relax",
+                "type": "GroupScene",
+                "group": "1",
+                "lights": ["1", "2", "3"],
+                "recycle": false,
+                "locked": true,
+                "lightstates": {
+                    "1": { "on": true, "bri": 144 },
+                    "3": { "on": true, "bri": 100 }
+                }
+            }
+        },
+        "schedules": {
+            "1": {
+                "name": "Wake up",
+                "description": "",
+                "command": { "address": "/api/olduser/groups/1/action", "method": "PUT", "body": { "scene": "abc" } },
+                "localtime": "W124/T07:00:00",
+                "status": "enabled",
+                "autodelete": false,
+                "created": "2019-01-05T10:00:00"
+            },
+            "2": {
+                "name": "Hallway off",
+                "description": "",
+                "command": { "address": "/api/olduser/lights/3/state", "method": "PUT", "body": { "on": false } },
+                "localtime": "T23:00:00",
+                "status": "enabled"
+            }
+        },
+        "rules": {
+            "1": {
+                "name": "Dimmer on",
+                "status": "enabled",
+                "recycle": false,
+                "timestriggered": 3,
+                "conditions": [
+                    { "address": "/sensors/5/state/buttonevent", "operator": "eq", "value": "1002" },
+                    { "address": "/sensors/5/state/lastupdated", "operator": "dx" },
+                    { "address": "/config/localtime", "operator": "in", "value": "T08:00:00/T20:00:00" }
+                ],
+                "actions": [{ "address": "/groups/1/action", "method": "PUT", "body": { "scene": "abc" } }]
+            },
+            "2": {
+                "name": "Motion",
+                "status": "enabled",
+                "recycle": false,
+                "conditions": [{ "address": "/sensors/9/state/presence", "operator": "eq", "value": "true" }],
+                "actions": [{ "address": "/groups/0/action", "method": "PUT", "body": { "on": true } }]
+            },
+            "3": {
+                "name": "Guests",
+                "status": "enabled",
+                "recycle": false,
+                "conditions": [{ "address": "/sensors/20/state/flag", "operator": "eq", "value": "true" }],
+                "actions": [{ "address": "/groups/0/action", "method": "PUT", "body": { "on": true } }]
+            }
+        },
+        "sensors": {
+            "20": {
+                "name": "Guest mode",
+                "type": "CLIPGenericFlag",
+                "modelid": "GUEST",
+                "manufacturername": "hueston",
+                "swversion": "1.0",
+                "uniqueid": "guestmode",
+                "state": { "flag": false, "lastupdated": "2019-05-02T18:02:00" },
+                "config": { "on": true, "reachable": true }
+            }
+        },
+        "physical_sensors": {
+            "1": { "name": "Daylight", "type": "Daylight", "unique_id": null },
+            "5": { "name": "Dimmer", "type": "ZLLSwitch", "unique_id": "00:17:88:01:10:5a:6b:7c-02-fc00" },
+            "9": { "name": "Motion", "type": "ZLLPresence", "unique_id": "00:17:88:01:02:0a:0b:0c-02-0406" }
+        },
+        "resourcelinks": {
+            "1": {
+                "name": "Dimmer setup",
+                "description": "",
+                "type": "Link",
+                "classid": 10020,
+                "recycle": false,
+                "links": ["/groups/1", "/scenes/abc", "/rules/1", "/rules/2", "/sensors/5", "/sensors/20"]
+            }
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn import_remaps_ids_to_the_new_bridge() {
+    let client = HueBridgeClient::from_cassette(cassette("import.json")).unwrap();
+    let report = client.import_datastore(&archive()).unwrap();
+
+    // Devices are matched, created resources get the IDs assigned by the bridge
+    let ids = |kind: &str| -> Vec<(&str, &str)> {
+        report.ids[kind].iter().map(|(old, new)| (old.as_str(), new.as_str())).collect()
+    };
+    assert_eq!(ids("lights"), vec![("1", "4"), ("2", "7")]);
+    assert_eq!(ids("sensors"), vec![("1", "1"), ("20", "31"), ("5", "12")]);
+    assert_eq!(ids("groups"), vec![("1", "2")]);
+    assert_eq!(ids("scenes"), vec![("abc", "xyz")]);
+    assert_eq!(ids("schedules"), vec![("1", "3")]);
+    assert_eq!(ids("rules"), vec![("1", "4"), ("3", "5")]);
+    assert_eq!(ids("resourcelinks"), vec![("1", "6")]);
+}
+
+#[test]
+fn import_reports_missing_devices() {
+    let client = HueBridgeClient::from_cassette(cassette("import.json")).unwrap();
+    let report = client.import_datastore(&archive()).unwrap();
+
+    // Missing devices are reported, and nothing that depends on them is created
+    let failures: Vec<(&str, String)> = report
+        .failures
+        .iter()
+        .map(|(address, err)| (address.as_str(), err.to_string()))
+        .collect();
+    assert_eq!(
+        failures,
+        vec![
+            ("/lights/3", "Unknown resource 00:17:88:01:03:2b:3c:4f-0b".to_string()),
+            ("/sensors/9", "Unknown resource 00:17:88:01:02:0a:0b:0c-02-0406".to_string()),
+            ("/schedules/2", "Unknown resource /lights/3".to_string()),
+            ("/rules/2", "Unknown resource /sensors/9".to_string()),
+        ]
+    );
+
+    // References in lists are left out instead of pointing at other devices
+    let dropped: Vec<(&str, &str)> = report
+        .dropped
+        .iter()
+        .map(|(address, reference)| (address.as_str(), reference.as_str()))
+        .collect();
+    assert_eq!(
+        dropped,
+        vec![
+            ("/groups/1", "/lights/3"),
+            ("/scenes/abc", "/lights/3"),
+            ("/resourcelinks/1", "/rules/2"),
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+use hueston::{HueBridgeClient, HueError, HueErrorCode};
+use std::path::PathBuf;
+
+/// Get the path of a cassette fixture.
+fn cassette(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/cassettes").join(name)
+}
+
+#[test]
+fn replay_lights() {
+    let client = HueBridgeClient::from_cassette(cassette("lights.json")).unwrap();
+    let lights = client.fetch_lights().unwrap();
+    assert_eq!(lights.len(), 2);
+
+    // Color lights report all attributes
+    let living = &lights[0];
+    assert_eq!(living.get_id(), 1);
+    assert_eq!(living.get_name(), "Living Room");
+    assert_eq!(living.get_model_id(), "LCT015");
+    assert_eq!(living.get_sw_version(), "1.46.13_r26312");
+    assert!(living.is_on());
+    assert_eq!(living.get_brightness(), 144);
+    assert_eq!(living.get_xy(), [0.5016, 0.4151]);
+    assert_eq!(living.get_color_mode().map(String::as_str), Some("xy"));
+    assert!(living.get_sw_update().is_some());
+
+    // Missing and null attributes fall back to their defaults
+    let hallway = &lights[1];
+    assert_eq!(hallway.get_id(), 3);
+    assert!(!hallway.is_on());
+    assert!(!hallway.is_reachable());
+    assert_eq!(hallway.get_hue(), 0);
+    assert_eq!(hallway.get_color_mode(), None);
+    assert_eq!(hallway.get_manufacturer_name(), "");
+    assert_eq!(hallway.get_sw_version(), "");
+}
+
+#[test]
+fn replay_serves_each_interaction_once() {
+    let client = HueBridgeClient::from_cassette(cassette("lights.json")).unwrap();
+    client.fetch_lights().unwrap();
+    match client.fetch_lights() {
+        Err(HueError::NotRecorded { method, path }) => {
+            assert_eq!(method, "GET");
+            assert_eq!(path, "/api/<username>/lights");
+        }
+        other => panic!("Expected NotRecorded, got {:?}", other),
+    }
+}
+
+#[test]
+fn replay_registration() {
+    let mut client = HueBridgeClient::from_cassette(cassette("register.json")).unwrap();
+    assert!(client.get_username().is_none());
+    assert_eq!(client.register("test"), Err(HueErrorCode::LinkButtonNotPressed));
+    assert!(client.get_username().is_none());
+
+    // The redacted username is used for later requests
+    client.register("test").unwrap();
+    assert_eq!(client.get_username().map(String::as_str), Some("<username>"));
+    assert!(client.fetch_lights().unwrap().is_empty());
+}
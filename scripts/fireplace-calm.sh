@@ -1,2 +0,0 @@
-#!/usr/bin/env sh
-cargo run -- simulate fireplace --sp 0 --tb 4 --tv 1
\ No newline at end of file
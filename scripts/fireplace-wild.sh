@@ -1,2 +0,0 @@
-#!/usr/bin/env sh
-cargo run -- simulate fireplace --bb 210 --bv 5 --sbb 240 --sbv 10 --sp 0.01 --tb 2 --tv 1
\ No newline at end of file